@@ -44,6 +44,7 @@ pub fn build(_: TokenStream, item: &mut ItemTrait) -> TokenStream {
     let mut methods = vec![];
     let mut fields = TokenStream::new();
     let mut from_fields = TokenStream::new();
+    let mut deferred_fields = TokenStream::new();
     let mut shim_items = TokenStream::new();
     let mut reflected_items = TokenStream::new();
     for item in &item.items {
@@ -119,6 +120,32 @@ pub fn build(_: TokenStream, item: &mut ItemTrait) -> TokenStream {
             from_fields.extend(quote! {
                 #mident: { let object = object.clone(); DERIVE_alloc::boxed::Box::new(move |#inputs| #lock.#mident(#inputs)) },
             });
+            let output_ty = match &method.sig.output {
+                ReturnType::Type(_, ty) => ty.clone().into_token_stream(),
+                ReturnType::Default => quote!(()),
+            };
+            let resolve = if receiver.is_mutable().is_some() {
+                quote! {
+                    let object = object.get().await?;
+                    let output = object.lock().unwrap().#mident(#inputs);
+                }
+            } else {
+                quote! {
+                    let output = object.take().await?.#mident(#inputs);
+                }
+            };
+            deferred_fields.extend(quote! {
+                #mident: {
+                    let object = object.clone();
+                    DERIVE_alloc::boxed::Box::new(move |#inputs| {
+                        let object = object.clone();
+                        <#output_ty as ::vessels::kind::Flatten>::flatten(async move {
+                            #resolve
+                            Ok::<_, ::vessels::reflect::DeferredError>(output)
+                        })
+                    })
+                },
+            });
             shim_items.extend(quote! {
                 #sig {
                     (self.#mident)(#inputs)
@@ -240,6 +267,7 @@ pub fn build(_: TokenStream, item: &mut ItemTrait) -> TokenStream {
     let mut upcast_arms = TokenStream::new();
     let mut supertrait_ids = TokenStream::new();
     let mut derive_param_bounds = TokenStream::new();
+    let mut supertrait_transfers = TokenStream::new();
     for (idx, supertrait) in item.supertraits.iter().enumerate() {
         use TypeParamBound::Trait;
         if let Trait(supertrait) = supertrait {
@@ -291,6 +319,12 @@ pub fn build(_: TokenStream, item: &mut ItemTrait) -> TokenStream {
             from_fields.extend(quote! {
                 #id: DERIVE_alloc::sync::Arc::new(::std::sync::Mutex::new(DERIVE_alloc::boxed::Box::new(<dyn #path as ::vessels::reflect::Reflected>::Shim::from_instance(object)))),
             });
+            deferred_fields.extend(quote! {
+                #id: DERIVE_alloc::sync::Arc::new(::std::sync::Mutex::new(DERIVE_alloc::boxed::Box::new(<dyn #path as ::vessels::reflect::Reflected>::Shim::from_deferred(object.clone())))),
+            });
+            supertrait_transfers.extend(quote! {
+                .with_supertrait::<dyn #path>()
+            });
             derive_param_bounds.extend(quote! {
                 + #path
             });
@@ -325,6 +359,12 @@ pub fn build(_: TokenStream, item: &mut ItemTrait) -> TokenStream {
                        _marker: ::core::marker::PhantomData
                     }
                 }
+                #vis fn from_deferred<DERIVEPARAM: ?Sized + #ident<#params> + 'static>(object: DERIVE_alloc::sync::Arc<::vessels::reflect::Deferred<DERIVEPARAM>>) -> Self {
+                    _DERIVED_Shim {
+                       #deferred_fields
+                       _marker: ::core::marker::PhantomData
+                    }
+                }
             }
             #supertrait_impls
             impl<#kind_bounded_params> #ident<#params> for _DERIVED_Shim<#params> {
@@ -337,6 +377,17 @@ pub fn build(_: TokenStream, item: &mut ItemTrait) -> TokenStream {
                 type ErasedShim = _DERIVED_ErasedShim<#params>;
                 #[doc(hidden)]
                 const DO_NOT_IMPLEMENT_THIS_MARKER_TRAIT_MANUALLY: () = ();
+                #[doc(hidden)]
+                const IDENTITY: [u8; 32] = <DERIVE_alloc::boxed::Box<dyn #ident<#params>> as ::vessels::Kind>::USE_KIND_MACRO_TO_GENERATE_THIS_FIELD;
+                #[doc(hidden)]
+                fn into_transport(self: DERIVE_alloc::boxed::Box<Self>) -> ::vessels::reflect::Transport {
+                    ::vessels::reflect::encode_transport(self)
+                }
+                #[doc(hidden)]
+                fn from_transport(transport: ::vessels::reflect::Transport) -> ::core::result::Result<DERIVE_alloc::boxed::Box<dyn ::vessels::reflect::Erased>, ::vessels::reflect::CastError> {
+                    let object = ::vessels::reflect::Deferred::new(::vessels::reflect::decode_transport::<DERIVE_alloc::boxed::Box<dyn #ident<#params>>>(transport));
+                    Ok(DERIVE_alloc::boxed::Box::new(<_DERIVED_ErasedShim<#params>>::from(DERIVE_alloc::boxed::Box::new(<_DERIVED_Shim<#params>>::from_deferred(object)) as DERIVE_alloc::boxed::Box<dyn #ident<#params>>)) as DERIVE_alloc::boxed::Box<dyn ::vessels::reflect::Erased>)
+                }
             }
             impl<#kind_bounded_params> From<DERIVE_alloc::boxed::Box<dyn #ident<#params>>> for _DERIVED_ErasedShim<#params> {
                 fn from(input: DERIVE_alloc::boxed::Box<dyn #ident<#params>>) -> _DERIVED_ErasedShim<#params> {
@@ -358,6 +409,9 @@ pub fn build(_: TokenStream, item: &mut ItemTrait) -> TokenStream {
                         })
                     }
                 }
+                fn transfer(self: DERIVE_alloc::boxed::Box<Self>) -> ::vessels::reflect::Transfer {
+                    ::vessels::reflect::Transfer::new::<dyn #ident<#params>>(self.0)#supertrait_transfers
+                }
             }
             impl<#kind_bounded_params> ::vessels::reflect::Trait<::vessels::reflect::SomeTrait> for _DERIVED_ErasedShim<#params> {
                 fn call(&self, index: ::vessels::reflect::MethodIndex, mut args: DERIVE_alloc::vec::Vec<DERIVE_alloc::boxed::Box<dyn ::core::any::Any + Send + Sync>>) -> ::core::result::Result<DERIVE_alloc::boxed::Box<dyn ::core::any::Any + Send + Sync>, ::vessels::reflect::CallError> {
@@ -391,10 +445,10 @@ pub fn build(_: TokenStream, item: &mut ItemTrait) -> TokenStream {
                     ::vessels::reflect::Trait::supertraits(self.0.as_ref())
                 }
                 fn upcast_erased(self: DERIVE_alloc::boxed::Box<Self>, ty: ::core::any::TypeId) -> ::core::result::Result<DERIVE_alloc::boxed::Box<dyn ::vessels::reflect::Erased>, ::vessels::reflect::CastError> {
-                    ::vessels::reflect::Trait::upcast_erased(self, ty)
+                    ::vessels::reflect::Trait::upcast_erased(self.0, ty)
                 }
                 fn erase(self: DERIVE_alloc::boxed::Box<Self>) -> DERIVE_alloc::boxed::Box<dyn ::vessels::reflect::Erased> {
-                    ::vessels::reflect::Trait::erase(self)
+                    self
                 }
             }
             #[doc(hidden)]
@@ -0,0 +1,33 @@
+use vessels::{
+    channel::IdChannel,
+    core::run,
+    format::{ApplyDecode, ApplyEncode, Cbor},
+    kind::Infallible,
+    log, object,
+    reflect::{Cast, Erased, Trait},
+    OnTo,
+};
+
+#[object]
+pub trait Greeter {
+    fn greet(&self, name: String) -> Infallible<String>;
+}
+
+pub struct Implementor;
+
+impl Greeter for Implementor {
+    fn greet(&self, name: String) -> Infallible<String> {
+        Box::pin(async move { Ok(format!("hello, {}", name)) })
+    }
+}
+
+fn main() {
+    run(async move {
+        let erased = Trait::<dyn Greeter>::erase(Box::new(Implementor) as Box<dyn Greeter>);
+        let encoded = erased.on_to::<IdChannel>().await.encode::<Cbor>();
+        let decoded: Box<dyn Erased> = encoded.decode::<IdChannel, Cbor>().await.unwrap();
+        log!("received erased {}", decoded.name());
+        let greeter: Box<dyn Greeter> = decoded.downcast().unwrap();
+        log!("{}", greeter.greet("world".to_owned()).await.unwrap());
+    });
+}
@@ -0,0 +1,41 @@
+use vessels::{
+    channel::{IdChannel, LocalChannel},
+    core::run,
+    format::{ApplyDecode, ApplyEncode, Cbor},
+    kind::Infallible,
+    log, OnTo,
+};
+
+use std::time::Instant;
+
+type Call = Box<dyn Fn(u32) -> Infallible<u32> + Send + Sync>;
+
+const CALLS: u32 = 10_000;
+
+fn call() -> Call {
+    Box::new(|n| Box::pin(async move { Ok(n + 1) }))
+}
+
+async fn measure(name: &str, decoded: Call) {
+    let start = Instant::now();
+    for n in 0..CALLS {
+        assert_eq!((decoded)(n).await.unwrap(), n + 1);
+    }
+    log!("{}: {} calls in {:?}", name, CALLS, start.elapsed());
+}
+
+fn main() {
+    run(async move {
+        let local: Call = call()
+            .on_to::<LocalChannel>()
+            .await
+            .complete()
+            .await
+            .unwrap();
+        measure("local", local).await;
+
+        let encoded = call().on_to::<IdChannel>().await.encode::<Cbor>();
+        let decoded: Call = encoded.decode::<IdChannel, Cbor>().await.unwrap();
+        measure("cbor", decoded).await;
+    });
+}
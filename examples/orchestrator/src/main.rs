@@ -2,6 +2,7 @@ use vessels::{
     core::{
         data::Resource,
         hal::crypto::Hasher,
+        log::Router,
         orchestrator::{Module, Orchestrator},
         register, run, Core,
     },
@@ -25,11 +26,15 @@ pub fn main() {
         let orchestrator = Orchestrator::new().unwrap();
         register(|| Hasher::new().unwrap());
         let mut core = Core::new();
-        core.register(|| Box::new(Tester) as Box<dyn test_vessel::Test>);
+        core.register_object(|| Box::new(Tester) as Box<dyn test_vessel::Test>);
+        let mut handle = core.into_handle();
+        let mut scope = handle.scope();
+        scope.permit::<Box<dyn test_vessel::Test>>();
         let data: String = orchestrator
-            .instantiate(
+            .instantiate_logged(
                 Resource::new(Module::new(binary)).await.unwrap(),
-                core.into_handle(),
+                scope,
+                Router::stdout(),
             )
             .await
             .unwrap();
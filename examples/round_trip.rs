@@ -0,0 +1,49 @@
+use vessels::{
+    core::run,
+    format::{Cbor, Json},
+    kind::Infallible,
+    log,
+    testing::{round_trip, round_trip_with, Conditions},
+    Kind,
+};
+
+use std::time::Duration;
+
+#[derive(Kind)]
+struct Counter {
+    name: String,
+    values: Vec<u32>,
+    next: Box<dyn Fn(u32) -> Infallible<u32> + Send + Sync>,
+}
+
+fn counter() -> Counter {
+    Counter {
+        name: "counter".to_owned(),
+        values: (0..32).collect(),
+        next: Box::new(|n| Box::pin(async move { Ok(n + 1) })),
+    }
+}
+
+fn main() {
+    run(async move {
+        let decoded = round_trip::<Json, _>(counter()).await.unwrap();
+        assert_eq!((decoded.next)(1).await.unwrap(), 2);
+
+        for seed in 1..=16 {
+            let decoded = round_trip_with::<Cbor, _>(
+                counter(),
+                Conditions {
+                    reorder: 8,
+                    latency: Duration::from_millis(1),
+                    seed,
+                },
+            )
+            .await
+            .unwrap();
+            assert_eq!(decoded.name, "counter");
+            assert_eq!(decoded.values, (0..32).collect::<Vec<_>>());
+            assert_eq!((decoded.next)(seed as u32).await.unwrap(), seed as u32 + 1);
+        }
+        log!("all round trips succeeded");
+    });
+}
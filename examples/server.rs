@@ -1,15 +1,22 @@
 use vessels::{
     channel::IdChannel,
-    core::{hal::network::Server, run},
-    format::Cbor,
+    core::{
+        hal::network::{Formats, Server},
+        run,
+    },
+    format::{Bincode, Cbor, Json},
 };
 
 pub fn main() {
     run(async move {
         Server::new()
             .unwrap()
-            .listen::<String, IdChannel, Cbor>(
+            .listen_with::<String>(
                 "127.0.0.1:61200".parse().unwrap(),
+                Formats::new()
+                    .with::<IdChannel, Cbor>()
+                    .with::<IdChannel, Bincode>()
+                    .with::<IdChannel, Json>(),
                 Box::new(move || Box::pin(async move { "format".to_string() })),
             )
             .await
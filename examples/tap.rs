@@ -0,0 +1,37 @@
+use vessels::{
+    channel::{
+        id_channel::tap::{replay, Recorder, Tap},
+        IdChannel,
+    },
+    core::run,
+    format::Json,
+    kind::Infallible,
+    log, OnTo,
+};
+
+type Call = Box<dyn Fn(String) -> Infallible<String> + Send + Sync>;
+
+fn main() {
+    let call: Call = Box::new(|name| Box::pin(async move { Ok(format!("hello, {}", name)) }));
+
+    run(async move {
+        let recorder = Recorder::new();
+        let observer = recorder.observer();
+        let encoded = Tap::<_, Json>::from_channel(call.on_to::<IdChannel>().await, |frame| {
+            log!("{}", frame)
+        });
+        let decoded: Call = Tap::<_, Json>::new(encoded, move |frame| {
+            log!("{}", frame);
+            observer(frame);
+        })
+        .construct()
+        .await
+        .unwrap();
+        log!("{}", (decoded)("world".to_owned()).await.unwrap());
+
+        let frames = recorder.inbound();
+        let count = frames.len();
+        let _replayed: Call = replay::<_, Json>(frames).await.unwrap();
+        log!("reconstructed from {} recorded frames", count);
+    });
+}
@@ -1,5 +1,6 @@
-use vessels::{core::acquire, export};
+use vessels::{core::acquire, export, log};
 
 export! {
+    log!(Info; capability = "Test"; "acquiring capability");
     acquire::<Box<dyn test_vessel::Test>>().await.unwrap().test("hello".to_owned()).await
 }
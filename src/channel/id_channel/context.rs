@@ -5,8 +5,11 @@ use std::{
     sync::{Mutex, RwLock},
 };
 
-use crate::{channel::ForkHandle, Kind};
+use crate::{channel::ForkHandle, kind::Failure, Kind};
 
+use super::{Control, CONTROL, REGISTRY};
+
+use anyhow::Error;
 use weak_table::PtrWeakHashSet;
 
 use futures::{
@@ -18,13 +21,32 @@ struct ContextState {
     channel_types: HashMap<ForkHandle, TypeId>,
     unused_indices: Vec<ForkHandle>,
     failed: HashSet<ForkHandle>,
+    half_closed: HashSet<ForkHandle>,
+    deliveries: HashMap<ForkHandle, (u64, Option<u64>)>,
     next_index: ForkHandle,
 }
 
+impl ContextState {
+    fn new(next_index: ForkHandle) -> Self {
+        let mut channel_types = HashMap::new();
+        channel_types.insert(CONTROL, TypeId::of::<Control>());
+        ContextState {
+            channel_types,
+            unused_indices: vec![],
+            failed: HashSet::new(),
+            half_closed: HashSet::new(),
+            deliveries: HashMap::new(),
+            next_index,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Context {
     state: Arc<RwLock<ContextState>>,
     tasks: Arc<Mutex<HashMap<ForkHandle, PtrWeakHashSet<Weak<AtomicWaker>>>>>,
+    missing: Arc<Mutex<Option<ForkHandle>>>,
+    failure: Arc<Mutex<Option<Arc<Error>>>>,
 }
 
 pub(crate) struct WaitFor {
@@ -84,6 +106,7 @@ impl Context {
         let ty = state.channel_types.get(&id).cloned();
         if ty.is_none() {
             state.failed.insert(id);
+            *self.missing.lock().unwrap() = Some(id);
         } else {
             state.failed.remove(&id);
         }
@@ -92,28 +115,126 @@ impl Context {
 
     pub(crate) fn new() -> Self {
         Context {
-            state: Arc::new(RwLock::new(ContextState {
-                channel_types: HashMap::new(),
-                next_index: ForkHandle(0),
-                failed: HashSet::new(),
-                unused_indices: vec![],
-            })),
+            state: Arc::new(RwLock::new(ContextState::new(ForkHandle(0)))),
             tasks: Arc::new(Mutex::new(HashMap::new())),
+            missing: Arc::new(Mutex::new(None)),
+            failure: Arc::new(Mutex::new(None)),
         }
     }
 
     pub(crate) fn new_shim() -> Self {
         Context {
-            state: Arc::new(RwLock::new(ContextState {
-                channel_types: HashMap::new(),
-                failed: HashSet::new(),
-                next_index: ForkHandle(1),
-                unused_indices: vec![],
-            })),
+            state: Arc::new(RwLock::new(ContextState::new(ForkHandle(1)))),
             tasks: Arc::new(Mutex::new(HashMap::new())),
+            missing: Arc::new(Mutex::new(None)),
+            failure: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Returns a context sharing all state with this one except for the record of
+    /// which fork, if any, was missing in the last failed lookup.
+    pub(crate) fn attempt(&self) -> Self {
+        Context {
+            state: self.state.clone(),
+            tasks: self.tasks.clone(),
+            missing: Arc::new(Mutex::new(None)),
+            failure: self.failure.clone(),
+        }
+    }
+
+    /// Records that the connection failed with `cause`. Only the first failure is
+    /// kept, as any later ones are consequences of it.
+    pub(crate) fn fail(&self, cause: Error) {
+        self.failure
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| Arc::new(cause));
+    }
+
+    pub(crate) fn failure(&self) -> Option<Error> {
+        self.failure
+            .lock()
+            .unwrap()
+            .clone()
+            .map(|cause| Failure(cause).into())
+    }
+
+    /// Takes the fork whose type was missing in the last failed lookup, if any.
+    pub(crate) fn take_missing(&self) -> Option<ForkHandle> {
+        self.missing.lock().unwrap().take()
+    }
+
+    /// Records that one end of a fork has closed. Once both the local and the remote
+    /// end have closed, the handle is forgotten and, if it was allocated locally,
+    /// returned to the pool for reuse.
+    pub(crate) fn close(&self, handle: ForkHandle) {
+        let mut state = self.state.write().unwrap();
+        if state.half_closed.remove(&handle) {
+            state.channel_types.remove(&handle);
+            state.failed.remove(&handle);
+            state.deliveries.remove(&handle);
+            if handle.0 % 2 == state.next_index.0 % 2 {
+                state.unused_indices.push(handle);
+            }
+        } else {
+            state.half_closed.insert(handle);
         }
     }
 
+    /// Records the delivery of an item on `handle`, returning whether the remote end
+    /// has closed and every item it sent has now been delivered.
+    pub(crate) fn deliver(&self, handle: ForkHandle) -> bool {
+        let mut state = self.state.write().unwrap();
+        let delivery = state.deliveries.entry(handle).or_insert((0, None));
+        delivery.0 += 1;
+        delivery.1.map_or(false, |sent| delivery.0 >= sent)
+    }
+
+    /// Records that the remote end of `handle` closed after sending `sent` items,
+    /// returning whether all of them have already been delivered.
+    pub(crate) fn expect(&self, handle: ForkHandle, sent: u64) -> bool {
+        let mut state = self.state.write().unwrap();
+        let delivery = state.deliveries.entry(handle).or_insert((0, None));
+        delivery.1 = Some(sent);
+        delivery.0 >= sent
+    }
+
+    /// Returns the name of the item type registered for `handle`, if any.
+    pub(crate) fn type_name(&self, handle: ForkHandle) -> Option<&'static str> {
+        let ty = *self.state.read().unwrap().channel_types.get(&handle)?;
+        REGISTRY.name(ty)
+    }
+
+    /// Returns the number of distinct handles this side has allocated, which only
+    /// grows when no reclaimed handle is available for reuse.
+    #[cfg(test)]
+    pub(crate) fn allocated(&self) -> usize {
+        self.state.read().unwrap().next_index.0 as usize / 2
+    }
+
+    /// Returns the number of entries held for open or closing forks.
+    #[cfg(test)]
+    pub(crate) fn entries(&self) -> usize {
+        let state = self.state.read().unwrap();
+        state.channel_types.len()
+            + state.failed.len()
+            + state.half_closed.len()
+            + state.deliveries.len()
+            + self.tasks.lock().unwrap().len()
+    }
+
+    pub(crate) fn contains(&self, handle: ForkHandle) -> bool {
+        self.state
+            .read()
+            .unwrap()
+            .channel_types
+            .contains_key(&handle)
+    }
+
+    pub(crate) fn is_closing(&self, handle: ForkHandle) -> bool {
+        self.state.read().unwrap().half_closed.contains(&handle)
+    }
+
     pub(crate) fn create<K: Kind>(&self) -> ForkHandle {
         let mut state = self.state.write().unwrap();
         let tasks = self.tasks.lock().unwrap();
@@ -1,4 +1,4 @@
-use super::Context;
+use super::{Context, Control};
 
 use crate::{channel::ForkHandle, Kind, SerdeAny};
 
@@ -9,7 +9,7 @@ use serde::{
 
 use std::{collections::HashMap, sync::RwLock};
 
-use core::any::TypeId;
+use core::any::{type_name, TypeId};
 
 use lazy_static::lazy_static;
 
@@ -18,6 +18,7 @@ type DeserializeFn =
 
 pub(crate) struct Registry {
     items: RwLock<HashMap<TypeId, DeserializeFn>>,
+    names: RwLock<HashMap<TypeId, &'static str>>,
 }
 
 impl Registry {
@@ -37,6 +38,10 @@ impl Registry {
                     <T as serde::Deserialize>::deserialize(de)
                         .map(|v| Box::new(v) as Box<dyn SerdeAny>)
                 });
+                self.names
+                    .write()
+                    .unwrap()
+                    .insert(TypeId::of::<T>(), type_name::<T>());
             }
         }
     }
@@ -44,11 +49,20 @@ impl Registry {
     fn get(&self, ty: TypeId) -> Option<DeserializeFn> {
         self.items.read().unwrap().get(&ty).copied()
     }
+
+    pub(crate) fn name(&self, ty: TypeId) -> Option<&'static str> {
+        self.names.read().unwrap().get(&ty).copied()
+    }
 }
 
 lazy_static! {
-    pub(crate) static ref REGISTRY: Registry = Registry {
-        items: RwLock::new(HashMap::new()),
+    pub(crate) static ref REGISTRY: Registry = {
+        let registry = Registry {
+            items: RwLock::new(HashMap::new()),
+            names: RwLock::new(HashMap::new()),
+        };
+        registry.add_type::<Control>();
+        registry
     };
 }
 
@@ -63,12 +77,10 @@ impl<'de, 'a> DeserializeSeed<'de> for Id<'a> {
     {
         let mut deserializer = erased_serde::Deserializer::erase(deserializer);
         (REGISTRY
-            .get(
-                self.1
-                    .get(self.0)
-                    .ok_or(Error::custom(format!("ASYNC_WAIT {} ", (self.0).0)))?,
-            )
-            .ok_or(Error::custom("no deserializer in registry"))?)(&mut deserializer)
+            .get(self.1.get(self.0).ok_or_else(|| {
+                Error::custom(format!("no type is yet known for fork {}", (self.0).0))
+            })?)
+            .ok_or_else(|| Error::custom("no deserializer in registry"))?)(&mut deserializer)
         .map_err(Error::custom)
     }
 }
@@ -5,13 +5,23 @@ use super::{Context, Id};
 use serde::{
     de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor},
     ser::{SerializeMap, SerializeSeq, Serializer},
-    Serialize,
+    Deserialize, Serialize,
 };
 
 use crate::channel::ForkHandle;
 
 use core::fmt;
 
+/// The reserved channel that carries fork lifecycle messages. Fork handles are
+/// allocated in steps of two from zero or one, so this is never handed out in practice.
+pub(crate) const CONTROL: ForkHandle = ForkHandle(core::u32::MAX);
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum Control {
+    /// The sending end of the fork has closed after sending the given number of items.
+    Close(ForkHandle, u64),
+}
+
 pub struct Item(pub(crate) ForkHandle, pub(crate) Box<dyn SerdeAny>, Context);
 
 impl Serialize for Item {
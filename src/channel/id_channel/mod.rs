@@ -2,11 +2,14 @@ mod context;
 pub(crate) use context::Context;
 mod item;
 pub use item::Item;
+pub(crate) use item::{Control, CONTROL};
 mod id;
 pub(crate) use id::Id;
 use id::REGISTRY;
+pub mod tap;
 
 use alloc::sync::Arc;
+use anyhow::Error;
 use core::{
     fmt::{self, Display, Formatter},
     marker::PhantomData,
@@ -17,7 +20,7 @@ use futures::{
     channel::mpsc::{unbounded, SendError, UnboundedReceiver, UnboundedSender},
     future::ok,
     task::{Context as FContext, Poll},
-    Future as IFuture, FutureExt, Sink as ISink, SinkExt, Stream, StreamExt, TryFutureExt,
+    Future as IFuture, FutureExt, Sink as ISink, SinkExt, Stream, StreamExt,
 };
 use parking_lot::RawMutex;
 use serde::{de::DeserializeOwned, Serialize};
@@ -82,6 +85,35 @@ impl IdChannelHandle {
     fn remove_fork(&self, handle: ForkHandle) {
         self.in_channels.lock().unwrap().remove(&handle);
     }
+
+    /// Forwards the items a local fork end sends on to the underlying channel and,
+    /// once that end has been dropped, notifies the peer of how many were sent.
+    fn forward<T: Serialize + Sync + Send + 'static>(
+        &self,
+        handle: ForkHandle,
+        mut receiver: UnboundedReceiver<T>,
+    ) -> impl IFuture<Output = ()> {
+        let channel = self.clone();
+        async move {
+            let mut sent = 0;
+            while let Some(item) = receiver.next().await {
+                if channel
+                    .out_channel
+                    .unbounded_send(Item::new(handle, Box::new(item), channel.context.clone()))
+                    .is_err()
+                {
+                    return;
+                }
+                sent += 1;
+            }
+            channel.context.close(handle);
+            let _ = channel.out_channel.unbounded_send(Item::new(
+                CONTROL,
+                Box::new(Control::Close(handle, sent)),
+                channel.context.clone(),
+            ));
+        }
+    }
 }
 
 impl Stream for IdChannel {
@@ -123,6 +155,13 @@ pub enum IdChannelError {
     InvalidId(ForkHandle),
 }
 
+impl IdChannel {
+    fn close_remote(&self, handle: ForkHandle) {
+        self.in_channels.lock().unwrap().remove(&handle);
+        self.context.close(handle);
+    }
+}
+
 impl Drop for IdChannel {
     fn drop(&mut self) {
         self.in_channels.lock().unwrap().remove(&ForkHandle(0));
@@ -133,21 +172,38 @@ impl ISink<Item> for IdChannel {
     type Error = IdChannelError;
 
     fn start_send(self: Pin<&mut Self>, item: Item) -> Result<(), Self::Error> {
-        match self.in_channels.lock().unwrap().get_mut(&item.0) {
+        if item.0 == CONTROL {
+            if let Ok(control) = item.1.downcast::<Control>() {
+                match *control {
+                    Control::Close(handle, sent) => {
+                        if self.context.expect(handle, sent) {
+                            self.close_remote(handle);
+                        }
+                    }
+                }
+            }
+            return Ok(());
+        }
+        let id = item.0;
+        let mut in_channels = self.in_channels.lock().unwrap();
+        match in_channels.get_mut(&id) {
             Some(channel) => {
-                let (id, data) = (item.0, item.1);
-                let r = channel
-                    .0
-                    .as_mut()
-                    .start_send(data)
-                    .map_err(|e| IdChannelError::Channel(SinkStage::Send, id.clone(), e));
-                if r.is_ok() {
+                // a failed send means the local end of this fork has gone away
+                if channel.0.as_mut().start_send(item.1).is_err() {
+                    in_channels.remove(&id);
+                } else {
                     self.set_waker.flush.wake(&id);
                 }
-                r
             }
-            None => Err(IdChannelError::InvalidId(item.0)),
+            // the local end of this fork has already been dropped
+            None if self.context.contains(id) => {}
+            None => return Err(IdChannelError::InvalidId(id)),
+        }
+        drop(in_channels);
+        if self.context.deliver(id) {
+            self.close_remote(id);
         }
+        Ok(())
     }
     fn poll_ready(self: Pin<&mut Self>, cx: &mut FContext) -> Poll<Result<(), Self::Error>> {
         self.set_waker.ready.register(cx.waker());
@@ -212,6 +268,8 @@ impl ISink<Item> for IdChannel {
         if pending {
             Poll::Pending
         } else {
+            // every fork ends once the underlying channel has closed
+            in_channels.clear();
             Poll::Ready(Ok(()))
         }
     }
@@ -220,12 +278,19 @@ impl ISink<Item> for IdChannel {
 impl Waiter for Context {
     type Item = Item;
 
-    fn wait_for(&self, data: String) -> Future<()> {
-        Box::pin(self.wait_for(ForkHandle(data.parse().unwrap())))
+    fn attempt(&self) -> Self {
+        Context::attempt(self)
+    }
+    fn wait(&self) -> Option<Future<()>> {
+        self.take_missing()
+            .map(|id| Box::pin(self.wait_for(id)) as Future<()>)
     }
     fn predicate(&self, item: &Self::Item) -> bool {
         self.failed()
     }
+    fn fail(&self, cause: Error) {
+        Context::fail(self, cause)
+    }
 }
 
 impl<'de> IContext<'de> for IdChannel {
@@ -265,14 +330,13 @@ impl<'a, K: Kind> IShim<'a, IdChannel, K> for Shim<K> {
             in_channels: Arc::new(Mutex::new(HashMap::new())),
         };
         let fork = channel.get_fork::<K>(ForkHandle(0));
+        let in_channels = channel.in_channels.clone();
         let (sender, receiver) = channel.split();
-        spawn(receiver.map(Ok).forward(sink).unwrap_or_else(|_| panic!()));
-        spawn(
-            stream
-                .map(Ok)
-                .forward(sender)
-                .unwrap_or_else(|e| panic!(format!("{}", e))),
-        );
+        spawn(receiver.map(Ok).forward(sink).map(|_| ()));
+        spawn(stream.map(Ok).forward(sender).map(move |_| {
+            // whether the peer hung up or sent something invalid, every fork ends
+            in_channels.lock().unwrap().clear();
+        }));
         Box::pin(fork)
     }
 }
@@ -291,18 +355,12 @@ impl IdChannelHandle {
         REGISTRY.add_deconstruct::<K>();
         let id = self.context.create::<K>();
         let waker = self.set_waker.apply(|waker| waker.with_key(id.clone()));
-        let context = self.context.clone();
-        let out_channel = self.out_channel.clone();
         let in_channels = self.in_channels.clone();
+        let handle = self.clone();
 
         Box::pin(
             IdChannelFork::new(kind, self.clone(), id).map(move |(sender, receiver)| {
-                spawn(
-                    receiver
-                        .map(move |v| Ok(Item::new(id, Box::new(v), context.clone())))
-                        .forward(out_channel)
-                        .unwrap_or_else(|_| panic!()),
-                );
+                spawn(handle.forward(id, receiver));
                 let mut in_channels = in_channels.lock().unwrap();
                 in_channels.insert(
                     id,
@@ -326,7 +384,6 @@ impl IdChannelHandle {
     }
 
     fn get_fork<K: Kind>(&self, fork_ref: ForkHandle) -> Fallible<K, K::ConstructError> {
-        let out_channel = self.out_channel.clone();
         REGISTRY.add_construct::<K>();
         self.context.add::<K>(fork_ref);
         let (sender, ireceiver): (UnboundedSender<K::DeconstructItem>, _) = unbounded();
@@ -337,23 +394,17 @@ impl IdChannelHandle {
                 Err(_) => panic!(),
             }))
         });
-        self.in_channels.lock().unwrap().insert(
-            fork_ref.clone(),
-            (
-                Box::pin(isender.sink_map_err(|e: SendError| ChannelError(e.into()))),
-                self.set_waker
-                    .apply(|waker| waker.with_key(fork_ref.clone())),
-            ),
-        );
-        let ct = self.context.clone();
-        spawn(
-            ireceiver
-                .map(move |item: K::DeconstructItem| {
-                    Ok(Item::new(fork_ref, Box::new(item), ct.clone()))
-                })
-                .forward(out_channel)
-                .unwrap_or_else(|_| panic!()),
-        );
+        if !self.context.is_closing(fork_ref) {
+            self.in_channels.lock().unwrap().insert(
+                fork_ref.clone(),
+                (
+                    Box::pin(isender.sink_map_err(|e: SendError| ChannelError(e.into()))),
+                    self.set_waker
+                        .apply(|waker| waker.with_key(fork_ref.clone())),
+                ),
+            );
+        }
+        spawn(self.forward(fork_ref, ireceiver));
         Box::pin(K::construct(IdChannelFork {
             o: Box::pin(sender),
             i: Box::pin(receiver),
@@ -410,6 +461,9 @@ impl<
     fn get_fork<K: Kind>(&self, fork_ref: ForkHandle) -> Fallible<K, K::ConstructError> {
         self.channel.get_fork(fork_ref)
     }
+    fn failure(&self) -> Option<Error> {
+        self.channel.context.failure()
+    }
 }
 
 pub(crate) struct IdChannelFork<
@@ -469,7 +523,7 @@ impl<
                     channel,
                     sink_item: PhantomData,
                 })
-                .unwrap_or_else(|_| panic!()),
+                .map(|_| ()),
             );
             (sender, receiver)
         }
@@ -517,7 +571,7 @@ impl<
                 receiver
                     .map(move |v| Ok(Item::new(handle, Box::new(v), ct.clone())))
                     .forward(csender)
-                    .unwrap_or_else(|_| panic!()),
+                    .map(|_| ()),
             );
             spawn(
                 kind.deconstruct(IdChannelFork {
@@ -527,7 +581,7 @@ impl<
                     channel: channel.clone(),
                     sink_item: PhantomData,
                 })
-                .unwrap_or_else(|_| panic!()),
+                .map(|_| ()),
             );
             channel
         }
@@ -574,3 +628,58 @@ impl<
     > Channel<I, O> for IdChannelFork<I, O>
 {
 }
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::IdChannel;
+    use crate::{
+        format::{ApplyDecode, ApplyEncode, Cbor},
+        kind::Infallible,
+        OnTo,
+    };
+
+    use futures::executor::block_on;
+
+    type Call = Box<dyn Fn(u32) -> Infallible<u32> + Send + Sync>;
+
+    const CALLS: u32 = 10_000;
+    // forks of the last few calls may still be closing when they are counted
+    const SLACK: usize = 16;
+
+    #[test]
+    fn forks_are_reclaimed() {
+        block_on(async {
+            let call: Call = Box::new(|n| Box::pin(async move { Ok(n + 1) }));
+            let channel = call.on_to::<IdChannel>().await;
+            let context = channel.context.clone();
+            let forks = channel.in_channels.clone();
+            let entries = || context.entries() + forks.lock().unwrap().len();
+            let call: Call = channel
+                .encode::<Cbor>()
+                .decode::<IdChannel, Cbor>()
+                .await
+                .unwrap();
+            assert_eq!(call(0).await.unwrap(), 1);
+            let baseline = entries();
+            let allocated = context.allocated();
+            for n in 0..CALLS {
+                assert_eq!(call(n).await.unwrap(), n + 1);
+            }
+            assert!(
+                entries() <= baseline + SLACK,
+                "fork tables grew from {} to {} entries over {} calls",
+                baseline,
+                entries(),
+                CALLS
+            );
+            // every call forks its return value, so without reuse of reclaimed
+            // handles at least one handle per call would have been allocated
+            assert!(
+                context.allocated() <= allocated + SLACK,
+                "{} handles were allocated over {} calls",
+                context.allocated() - allocated,
+                CALLS
+            );
+        });
+    }
+}
@@ -0,0 +1,314 @@
+//! Observation and replay of the frames of an `IdChannel` session.
+//!
+//! A [`Tap`](struct.Tap.html) wraps an encoded transport and reports every frame
+//! that passes through it in either direction. A [`Recorder`](struct.Recorder.html)
+//! keeps those frames so that the frames received by the constructing side can
+//! later be fed back through [`replay`](fn.replay.html) to reproduce a construct.
+//! ```ignore
+//! use vessels::{
+//!     channel::id_channel::tap::{replay, Recorder, Tap},
+//!     format::Cbor,
+//!     log,
+//! };
+//!
+//! let recorder = Recorder::new();
+//! let observer = recorder.observer();
+//! let transport = Tap::<_, Cbor>::new(transport, move |frame| {
+//!     log!("{}", frame);
+//!     observer(frame);
+//! });
+//! let value: String = transport.construct().await.unwrap();
+//! let replayed: String = replay::<_, Cbor>(recorder.inbound()).await.unwrap();
+//! ```
+
+use super::{Context, IdChannel, Item};
+
+use crate::{
+    channel::{Context as IContext, ForkHandle, Target},
+    format::{decode_with, ApplyDecode, ApplyEncode, EncodeError, Format, UniformStreamSink},
+    kind::{Fallible, SinkStream},
+    ErrorBound, Kind,
+};
+
+use alloc::sync::Arc;
+use core::{
+    fmt::{self, Debug, Display, Formatter},
+    marker::PhantomData,
+    pin::Pin,
+};
+use futures::{
+    sink::drain,
+    stream::{iter, pending},
+    task::{Context as FContext, Poll},
+    FutureExt, Sink, SinkExt, Stream, StreamExt,
+};
+use serde::de::{DeserializeSeed, Deserializer, Error, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use std::sync::Mutex;
+use void::Void;
+
+/// The direction in which a frame passed through a `Tap`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// The frame was received from the transport.
+    Inbound,
+    /// The frame was sent on the transport.
+    Outbound,
+}
+
+/// A frame observed by a `Tap`.
+#[derive(Clone, Debug)]
+pub struct Frame<R> {
+    pub direction: Direction,
+    /// The fork the frame belongs to, if it could be read from the frame. This
+    /// requires a self-describing `Format`.
+    pub fork: Option<ForkHandle>,
+    /// The name of the item type registered for that fork, if the tap knows the
+    /// context of the channel on the local end of the transport.
+    pub ty: Option<&'static str>,
+    pub data: R,
+}
+
+impl<R: Debug> Display for Frame<R> {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        let direction = match self.direction {
+            Direction::Inbound => "<-",
+            Direction::Outbound => "->",
+        };
+        match self.fork {
+            Some(fork) => write!(formatter, "{} fork {}", direction, fork)?,
+            None => write!(formatter, "{} unknown fork", direction)?,
+        }
+        if let Some(ty) = self.ty {
+            write!(formatter, " ({})", ty)?;
+        }
+        write!(formatter, ": {:?}", self.data)
+    }
+}
+
+/// Wraps an encoded transport, reporting every frame sent or received on it to
+/// an observer.
+pub struct Tap<C, F: Format> {
+    transport: C,
+    observer: Box<dyn Fn(Frame<F::Representation>) + Sync + Send>,
+    context: Option<Context>,
+    _marker: PhantomData<fn() -> F>,
+}
+
+impl<C, F: Format + 'static> Tap<C, F>
+where
+    F::Representation: Clone,
+{
+    pub fn new(
+        transport: C,
+        observer: impl Fn(Frame<F::Representation>) + Sync + Send + 'static,
+    ) -> Self {
+        Tap {
+            transport,
+            observer: Box::new(observer),
+            context: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Resolves the registered type of each frame's fork using the context of
+    /// the channel on the local end of the transport, as returned by
+    /// `IdChannel::context`. This is only needed for a transport not created
+    /// with `from_channel` nor consumed with `construct`, which supply the
+    /// context themselves.
+    pub fn with_context(mut self, context: Context) -> Self {
+        self.context = Some(context);
+        self
+    }
+
+    fn observe(&self, direction: Direction, data: &F::Representation) {
+        let fork = F::deserialize(data.clone(), Header)
+            .now_or_never()
+            .and_then(Result::ok);
+        let ty = fork.and_then(|fork| self.context.as_ref()?.type_name(fork));
+        (self.observer)(Frame {
+            direction,
+            fork,
+            ty,
+            data: data.clone(),
+        });
+    }
+}
+
+impl<F: Format + 'static>
+    Tap<SinkStream<F::Representation, EncodeError<F, Item, IdChannel>, F::Representation>, F>
+where
+    F::Representation: Clone + Sync + Send,
+{
+    /// Encodes `channel` with `F` and taps the resulting transport.
+    pub fn from_channel(
+        channel: IdChannel,
+        observer: impl Fn(Frame<F::Representation>) + Sync + Send + 'static,
+    ) -> Self {
+        let context = channel.context.clone();
+        Tap::new(channel.encode::<F>(), observer).with_context(context)
+    }
+}
+
+impl<C, F: Format + 'static> Tap<C, F>
+where
+    C: UniformStreamSink<F::Representation> + Sync + Send + Unpin + 'static,
+    <C as Sink<F::Representation>>::Error: ErrorBound,
+    F::Representation: Clone + Sync + Send + 'static,
+{
+    /// Constructs a `Kind` from the tapped transport on a new `IdChannel`.
+    pub fn construct<K: Kind>(mut self) -> Fallible<K, K::ConstructError> {
+        let shim = <IdChannel as Target<'static, K>>::new_shim();
+        self.context = Some(shim.context());
+        decode_with::<F, _, K, IdChannel, _>(self, shim)
+    }
+}
+
+impl<C: Stream<Item = F::Representation> + Unpin, F: Format + 'static> Stream for Tap<C, F>
+where
+    F::Representation: Clone,
+{
+    type Item = F::Representation;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut FContext) -> Poll<Option<Self::Item>> {
+        let item = self.transport.poll_next_unpin(cx);
+        if let Poll::Ready(Some(data)) = &item {
+            self.observe(Direction::Inbound, data);
+        }
+        item
+    }
+}
+
+impl<C: Sink<F::Representation> + Unpin, F: Format + 'static> Sink<F::Representation> for Tap<C, F>
+where
+    F::Representation: Clone,
+{
+    type Error = C::Error;
+
+    fn start_send(mut self: Pin<&mut Self>, item: F::Representation) -> Result<(), Self::Error> {
+        self.observe(Direction::Outbound, &item);
+        self.transport.start_send_unpin(item)
+    }
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut FContext) -> Poll<Result<(), Self::Error>> {
+        self.transport.poll_ready_unpin(cx)
+    }
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut FContext) -> Poll<Result<(), Self::Error>> {
+        self.transport.poll_flush_unpin(cx)
+    }
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut FContext) -> Poll<Result<(), Self::Error>> {
+        self.transport.poll_close_unpin(cx)
+    }
+}
+
+/// Keeps every frame reported to its observers.
+pub struct Recorder<R> {
+    frames: Arc<Mutex<Vec<Frame<R>>>>,
+}
+
+impl<R> Clone for Recorder<R> {
+    fn clone(&self) -> Self {
+        Recorder {
+            frames: self.frames.clone(),
+        }
+    }
+}
+
+impl<R: Clone + Send + 'static> Default for Recorder<R> {
+    fn default() -> Self {
+        Recorder::new()
+    }
+}
+
+impl<R: Clone + Send + 'static> Recorder<R> {
+    pub fn new() -> Self {
+        Recorder {
+            frames: Arc::new(Mutex::new(vec![])),
+        }
+    }
+
+    /// Returns an observer for a `Tap` that records into this recorder.
+    pub fn observer(&self) -> impl Fn(Frame<R>) + Sync + Send + 'static {
+        let frames = self.frames.clone();
+        move |frame| frames.lock().unwrap().push(frame)
+    }
+
+    /// Returns every frame recorded so far, in the order observed.
+    pub fn frames(&self) -> Vec<Frame<R>> {
+        self.frames.lock().unwrap().clone()
+    }
+
+    /// Returns the data of every inbound frame recorded so far, in the order
+    /// received, as accepted by `replay`.
+    pub fn inbound(&self) -> Vec<R> {
+        self.frames
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|frame| frame.direction == Direction::Inbound)
+            .map(|frame| frame.data.clone())
+            .collect()
+    }
+}
+
+/// Reconstructs a `Kind` from the frames its constructing side received in a
+/// recorded session. Frames are delivered in the order given and anything the
+/// reconstructed `Kind` sends is discarded. The session is held open after the
+/// last frame, so the reconstructed `Kind` is left in the state it was in when
+/// the recording ended.
+pub fn replay<K: Kind, F: Format + 'static>(
+    frames: impl IntoIterator<Item = F::Representation>,
+) -> Fallible<K, K::ConstructError>
+where
+    F::Representation: Clone + Sync + Send + 'static,
+{
+    let frames: Vec<_> = frames.into_iter().collect();
+    SinkStream::new(
+        drain().sink_map_err(|e| -> Void { match e {} }),
+        iter(frames).chain(pending()),
+    )
+    .decode::<IdChannel, F>()
+}
+
+/// Reads the fork of an encoded item while skipping its contents.
+struct Header;
+
+impl<'de> DeserializeSeed<'de> for Header {
+    type Value = ForkHandle;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<ForkHandle, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_map(HeaderVisitor)
+        } else {
+            deserializer.deserialize_seq(HeaderVisitor)
+        }
+    }
+}
+
+struct HeaderVisitor;
+
+impl<'de> Visitor<'de> for HeaderVisitor {
+    type Value = ForkHandle;
+
+    fn expecting(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        write!(formatter, "a channel item")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let channel = seq
+            .next_element()?
+            .ok_or_else(|| A::Error::invalid_length(0, &"two elements"))?;
+        while seq.next_element::<IgnoredAny>()?.is_some() {}
+        Ok(channel)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut channel = None;
+        while let Some(key) = map.next_key::<String>()? {
+            if key == "channel" {
+                channel = Some(map.next_value()?);
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        channel.ok_or_else(|| A::Error::missing_field("channel"))
+    }
+}
@@ -0,0 +1,307 @@
+use alloc::sync::Arc;
+use anyhow::Error;
+use core::{
+    any::Any,
+    marker::PhantomData,
+    pin::Pin,
+    sync::atomic::{AtomicU32, Ordering},
+};
+use futures::{
+    channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender},
+    future::ok,
+    task::{Context as FContext, Poll},
+    FutureExt, Sink as ISink, Stream, StreamExt,
+};
+use serde::{
+    de::{DeserializeOwned, DeserializeSeed, Deserializer, Error as DeError},
+    ser::{Error as SerError, Serializer},
+    Serialize,
+};
+use std::{collections::HashMap, sync::Mutex};
+use void::Void;
+
+use crate::{
+    channel::{Channel, ChannelError, Context as IContext, Fork, ForkHandle, Waiter},
+    core::spawn,
+    kind::{Fallible, Future},
+    Kind, Target,
+};
+
+use super::Shim as IShim;
+
+type Ends<K> = (
+    UnboundedReceiver<<K as Kind>::ConstructItem>,
+    UnboundedSender<<K as Kind>::DeconstructItem>,
+);
+
+/// The forks of a `LocalChannel` that have been created but not yet
+/// constructed, keyed by handle.
+#[derive(Clone)]
+struct Registry {
+    pending: Arc<Mutex<HashMap<ForkHandle, Box<dyn Any + Send>>>>,
+    unused_indices: Arc<Mutex<Vec<ForkHandle>>>,
+    next_index: Arc<AtomicU32>,
+}
+
+impl Registry {
+    fn new() -> Self {
+        Registry {
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            unused_indices: Arc::new(Mutex::new(vec![])),
+            next_index: Arc::new(AtomicU32::new(0)),
+        }
+    }
+
+    fn fork<K: Kind>(&self, kind: K) -> Fallible<ForkHandle, K::DeconstructError> {
+        let (sender, ireceiver) = unbounded();
+        let (isender, receiver) = unbounded();
+        let handle = self
+            .unused_indices
+            .lock()
+            .unwrap()
+            .pop()
+            .unwrap_or_else(|| ForkHandle(self.next_index.fetch_add(1, Ordering::Relaxed)));
+        self.pending
+            .lock()
+            .unwrap()
+            .insert(handle, Box::new((ireceiver, isender) as Ends<K>));
+        spawn(
+            kind.deconstruct(LocalFork {
+                i: receiver,
+                o: sender,
+                registry: self.clone(),
+            })
+            .map(|_| ()),
+        );
+        Box::pin(ok(handle))
+    }
+
+    fn get_fork<K: Kind>(&self, fork_ref: ForkHandle) -> Fallible<K, K::ConstructError> {
+        let ends = self.pending.lock().unwrap().remove(&fork_ref);
+        let (i, o) = match ends.and_then(|ends| ends.downcast::<Ends<K>>().ok()) {
+            Some(ends) => {
+                self.unused_indices.lock().unwrap().push(fork_ref);
+                *ends
+            }
+            // an unknown fork is constructed as though its peer had already gone away
+            None => (unbounded().1, unbounded().0),
+        };
+        Box::pin(K::construct(LocalFork {
+            i,
+            o,
+            registry: self.clone(),
+        }))
+    }
+}
+
+/// One end of a fork of a `LocalChannel`, which passes items to the other end
+/// directly rather than serializing them.
+pub(crate) struct LocalFork<I, O> {
+    i: UnboundedReceiver<I>,
+    o: UnboundedSender<O>,
+    registry: Registry,
+}
+
+impl<I, O> Stream for LocalFork<I, O> {
+    type Item = I;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut FContext) -> Poll<Option<Self::Item>> {
+        self.i.poll_next_unpin(cx)
+    }
+}
+
+impl<I, O> ISink<O> for LocalFork<I, O> {
+    type Error = ChannelError;
+
+    fn start_send(mut self: Pin<&mut Self>, item: O) -> Result<(), Self::Error> {
+        Pin::new(&mut self.o)
+            .start_send(item)
+            .map_err(|e| ChannelError(e.into()))
+    }
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut FContext) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.o)
+            .poll_ready(cx)
+            .map_err(|e| ChannelError(e.into()))
+    }
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut FContext) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.o)
+            .poll_flush(cx)
+            .map_err(|e| ChannelError(e.into()))
+    }
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut FContext) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.o)
+            .poll_close(cx)
+            .map_err(|e| ChannelError(e.into()))
+    }
+}
+
+impl<I: Send + 'static, O: Send + 'static> Fork for LocalFork<I, O> {
+    fn fork<K: Kind>(&self, kind: K) -> Fallible<ForkHandle, K::DeconstructError> {
+        self.registry.fork(kind)
+    }
+    fn get_fork<K: Kind>(&self, fork_ref: ForkHandle) -> Fallible<K, K::ConstructError> {
+        self.registry.get_fork(fork_ref)
+    }
+}
+
+impl<
+        I: Serialize + DeserializeOwned + Sync + Send + 'static,
+        O: Serialize + DeserializeOwned + Sync + Send + 'static,
+    > Channel<I, O> for LocalFork<I, O>
+{
+}
+
+/// The single item a `LocalChannel` yields, handing the root fork to its shim.
+///
+/// This item never leaves the process and serializing it returns an error. As
+/// `Format::serialize` is infallible, encoding a `LocalChannel` with some `Format`
+/// panics on its first item.
+pub struct Item(Registry, ForkHandle);
+
+impl Serialize for Item {
+    fn serialize<S: Serializer>(&self, _: S) -> Result<S::Ok, S::Error> {
+        Err(S::Error::custom(
+            "items of a LocalChannel cannot be serialized",
+        ))
+    }
+}
+
+/// The deserialization context of a `LocalChannel`. As its items are never
+/// serialized, deserialization through this context always fails.
+#[derive(Clone)]
+pub struct Context;
+
+impl<'de> DeserializeSeed<'de> for Context {
+    type Value = Item;
+
+    fn deserialize<D: Deserializer<'de>>(self, _: D) -> Result<Self::Value, D::Error> {
+        Err(D::Error::custom(
+            "items of a LocalChannel cannot be deserialized",
+        ))
+    }
+}
+
+impl Waiter for Context {
+    type Item = Item;
+
+    fn attempt(&self) -> Self {
+        Context
+    }
+    fn wait(&self) -> Option<Future<()>> {
+        None
+    }
+    fn predicate(&self, _: &Self::Item) -> bool {
+        false
+    }
+    fn fail(&self, _: Error) {}
+}
+
+/// A `Target` that keeps both ends of a `Kind` in the same process.
+///
+/// Items are passed between the constructed and deconstructed ends of each fork
+/// through typed queues, without boxing or any `Format`. It must not be encoded.
+/// ```
+/// use futures::executor::block_on;
+/// use vessels::{channel::LocalChannel, OnTo};
+///
+/// block_on(async {
+///     let channel = "test".to_owned().on_to::<LocalChannel>().await;
+///     let value: String = channel.complete().await.unwrap();
+///     assert_eq!(value, "test");
+/// });
+/// ```
+pub struct LocalChannel {
+    item: Option<Item>,
+}
+
+impl LocalChannel {
+    /// Constructs the `Kind` this channel was created with.
+    pub fn complete<K: Kind>(self) -> Fallible<K, K::ConstructError> {
+        <Self as Target<K>>::new_shim().complete(self)
+    }
+}
+
+impl Stream for LocalChannel {
+    type Item = Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, _: &mut FContext) -> Poll<Option<Self::Item>> {
+        Poll::Ready(self.item.take())
+    }
+}
+
+impl ISink<Item> for LocalChannel {
+    type Error = Void;
+
+    fn start_send(self: Pin<&mut Self>, _: Item) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn poll_ready(self: Pin<&mut Self>, _: &mut FContext) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+    fn poll_flush(self: Pin<&mut Self>, _: &mut FContext) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+    fn poll_close(self: Pin<&mut Self>, _: &mut FContext) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<'de> IContext<'de> for LocalChannel {
+    type Item = Item;
+    type Target = Context;
+
+    fn context(&self) -> Self::Target {
+        Context
+    }
+}
+
+impl<'a, K: Kind> Target<'a, K> for LocalChannel {
+    type Shim = Shim<K>;
+
+    fn new_with(kind: K) -> Future<Self>
+    where
+        K::DeconstructFuture: Send,
+    {
+        let registry = Registry::new();
+        let root = registry.fork(kind);
+        Box::pin(async move {
+            LocalChannel {
+                item: root.await.ok().map(|root| Item(registry, root)),
+            }
+        })
+    }
+
+    fn new_shim() -> Self::Shim {
+        Shim {
+            _marker: PhantomData,
+        }
+    }
+}
+
+pub struct Shim<K: Kind> {
+    _marker: PhantomData<K>,
+}
+
+impl<'a, K: Kind> IShim<'a, LocalChannel, K> for Shim<K> {
+    fn complete<C: Sync + Send + Stream<Item = Item> + ISink<Item> + 'static>(
+        self,
+        input: C,
+    ) -> Fallible<K, K::ConstructError> {
+        Box::pin(async move {
+            let (item, _) = Box::pin(input).into_future().await;
+            match item {
+                Some(Item(registry, root)) => registry.get_fork(root).await,
+                None => Registry::new().get_fork(ForkHandle(0)).await,
+            }
+        })
+    }
+}
+
+impl<'a, K: Kind> IContext<'a> for Shim<K> {
+    type Item = Item;
+    type Target = Context;
+
+    fn context(&self) -> Self::Target {
+        Context
+    }
+}
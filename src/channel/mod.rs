@@ -1,5 +1,7 @@
 pub mod id_channel;
 pub use id_channel::IdChannel;
+pub mod local_channel;
+pub use local_channel::LocalChannel;
 
 use crate::{
     kind::{Fallible, Future},
@@ -28,6 +30,11 @@ impl Display for ForkHandle {
 pub trait Fork: Sync + Send + 'static {
     fn fork<K: Kind>(&self, kind: K) -> Fallible<ForkHandle, K::DeconstructError>;
     fn get_fork<K: Kind>(&self, fork_ref: ForkHandle) -> Fallible<K, K::ConstructError>;
+    /// Returns the cause of the failure of the underlying connection, if it has
+    /// failed rather than closed.
+    fn failure(&self) -> Option<Error> {
+        None
+    }
 }
 
 #[derive(Debug, Error)]
@@ -71,11 +78,23 @@ pub trait Target<'a, K: Kind>: Context<'a> + Sized + Send + Sync {
     fn new_shim() -> Self::Shim;
 }
 
-pub trait Waiter {
+/// The context against which items are deserialized, which may be unable to
+/// deserialize an item until state it depends on, such as the type of a fork
+/// that has not yet been created, becomes available.
+pub trait Waiter: Sized {
     type Item;
 
-    fn wait_for(&self, data: String) -> Future<()>;
+    /// Returns a copy of this context to be used for a single deserialization
+    /// attempt, so that `wait` reports on that attempt alone.
+    fn attempt(&self) -> Self;
+    /// If the last deserialization through this attempt failed only because the
+    /// state it depends on is not yet available, returns a future that resolves
+    /// once that state is available and the attempt may be retried.
+    fn wait(&self) -> Option<Future<()>>;
     fn predicate(&self, item: &Self::Item) -> bool;
+    /// Records that the connection failed with `cause`, which is then reported
+    /// by the forks that end as a result.
+    fn fail(&self, cause: Error);
 }
 
 pub trait Context<'de> {
@@ -0,0 +1,170 @@
+use serde::{Deserialize, Serialize};
+
+/// The version of the connection protocol. Peers speaking different versions
+/// refuse to connect rather than fail later in the session.
+pub(crate) const PROTOCOL_VERSION: u32 = 1;
+
+/// The first frame of a connection, sent by the client.
+///
+/// Handshake frames are always encoded with `Cbor` so that they can be read
+/// before a format has been agreed upon.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Hello {
+    pub(crate) version: u32,
+    /// The hash of the root `Kind` the client expects to construct.
+    pub(crate) kind: [u8; 32],
+    /// The formats the client supports, in order of preference.
+    pub(crate) formats: Vec<String>,
+    pub(crate) resume: Resume,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum Resume {
+    /// The client does not want a resumable session.
+    Never,
+    /// The client wants a resumable session if the server supports them.
+    Request,
+    /// The client is resuming the identified session, of which it has received
+    /// the contained number of frames.
+    Session(Vec<u8>, u64),
+}
+
+/// The server's response to a `Hello`.
+#[derive(Serialize, Deserialize)]
+pub(crate) enum Reply {
+    /// The connection proceeds using the named format.
+    Accept(String),
+    /// The connection proceeds as the identified resumable session using the
+    /// named format.
+    Session(String, Vec<u8>),
+    /// The session is resumed. The server has received the contained number of
+    /// its frames.
+    Resumed(u64),
+    Reject(Rejection),
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum Rejection {
+    /// The server speaks the contained protocol version.
+    Version(u32),
+    KindMismatch,
+    /// The server supports only the contained formats.
+    NoCommonFormat(Vec<String>),
+    /// The session to be resumed is unknown to the server or has expired.
+    UnknownSession,
+}
+
+impl Hello {
+    /// Checks the protocol version and the hash of the root `Kind` against those
+    /// of the server.
+    pub(crate) fn check(&self, kind: [u8; 32]) -> Result<(), Rejection> {
+        if self.version != PROTOCOL_VERSION {
+            return Err(Rejection::Version(PROTOCOL_VERSION));
+        }
+        if self.kind != kind {
+            return Err(Rejection::KindMismatch);
+        }
+        Ok(())
+    }
+
+    /// Chooses the first of the client's formats that the server supports.
+    pub(crate) fn choose(&self, supported: &[String]) -> Result<String, Rejection> {
+        self.formats
+            .iter()
+            .find(|format| supported.contains(format))
+            .cloned()
+            .ok_or_else(|| Rejection::NoCommonFormat(supported.to_vec()))
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::{Hello, Rejection, Resume, PROTOCOL_VERSION};
+    use crate::format::{Cbor, Format};
+
+    use core::marker::PhantomData;
+    use futures::executor::block_on;
+
+    const KIND: [u8; 32] = [1; 32];
+
+    fn hello(formats: &[&str]) -> Hello {
+        Hello {
+            version: PROTOCOL_VERSION,
+            kind: KIND,
+            formats: formats.iter().map(|format| format.to_string()).collect(),
+            resume: Resume::Never,
+        }
+    }
+
+    fn names(formats: &[&str]) -> Vec<String> {
+        formats.iter().map(|format| format.to_string()).collect()
+    }
+
+    #[test]
+    fn matching_hello_is_accepted() {
+        assert!(hello(&["cbor"]).check(KIND).is_ok());
+    }
+
+    #[test]
+    fn version_mismatch_is_rejected() {
+        let mut hello = hello(&["cbor"]);
+        hello.version = PROTOCOL_VERSION + 1;
+        match hello.check(KIND) {
+            Err(Rejection::Version(version)) => assert_eq!(version, PROTOCOL_VERSION),
+            _ => panic!("a hello of another protocol version was not rejected"),
+        }
+    }
+
+    #[test]
+    fn kind_mismatch_is_rejected() {
+        match hello(&["cbor"]).check([2; 32]) {
+            Err(Rejection::KindMismatch) => {}
+            _ => panic!("a hello expecting another Kind was not rejected"),
+        }
+    }
+
+    #[test]
+    fn client_preference_chooses_format() {
+        let hello = hello(&["json", "cbor", "bincode"]);
+        assert_eq!(
+            hello.choose(&names(&["bincode", "cbor"])).ok(),
+            Some("cbor".to_owned())
+        );
+        assert_eq!(
+            hello.choose(&names(&["bincode", "json"])).ok(),
+            Some("json".to_owned())
+        );
+    }
+
+    #[test]
+    fn no_common_format_is_rejected() {
+        match hello(&["json"]).choose(&names(&["cbor", "bincode"])) {
+            Err(Rejection::NoCommonFormat(supported)) => {
+                assert_eq!(supported, names(&["cbor", "bincode"]))
+            }
+            _ => panic!("a hello with no supported format was not rejected"),
+        }
+    }
+
+    #[test]
+    fn hello_survives_cbor() {
+        let mut sent = hello(&["json", "cbor"]);
+        sent.resume = Resume::Session(vec![7; 16], 42);
+        let received = block_on(Cbor::deserialize(
+            Cbor::serialize(&sent),
+            PhantomData::<Hello>,
+        ))
+        .ok()
+        .unwrap();
+        assert_eq!(received.version, PROTOCOL_VERSION);
+        assert_eq!(received.kind, KIND);
+        assert_eq!(received.formats, names(&["json", "cbor"]));
+        match received.resume {
+            Resume::Session(id, received) => {
+                assert_eq!(id, vec![7; 16]);
+                assert_eq!(received, 42);
+            }
+            _ => panic!("the resumption request was lost"),
+        }
+    }
+}
@@ -1,17 +1,32 @@
 use crate::{
     channel::{Context, OnTo, Target},
     core::{spawn, UnimplementedError},
-    format::{ApplyDecode, ApplyEncode, Format},
+    format::{ApplyDecode, ApplyEncode, Cbor, Format},
     kind::{Fallible, Future, Infallible, SinkStream, TransportError},
     object, Kind,
 };
 
-use anyhow::Error;
-use futures::{future::ready, lock::Mutex, FutureExt, Sink, StreamExt};
+use anyhow::{anyhow, Error};
+use core::marker::PhantomData;
+use futures::{
+    future::{ok, ready},
+    lock::Mutex,
+    FutureExt, Sink, SinkExt, StreamExt,
+};
 use std::{net::SocketAddr, sync::Arc};
 use thiserror::Error;
 use url::Url;
 
+mod handshake;
+use handshake::{Hello, Rejection, Reply, Resume, PROTOCOL_VERSION};
+mod session;
+pub use session::Resumption;
+#[cfg(all(not(target_arch = "wasm32"), feature = "core"))]
+pub mod secure;
+#[cfg(all(not(target_arch = "wasm32"), feature = "core"))]
+use secure::{secure, Identity, PublicKey, Role, SecureError};
+use session::{Link, Sessions};
+
 #[object]
 pub trait Peer {}
 
@@ -23,6 +38,18 @@ pub enum ConnectError {
     Construct(#[source] Error),
     #[error("underlying transport failed: {0}")]
     Transport(#[from] TransportError),
+    #[error("handshake failed: {0}")]
+    Handshake(#[source] Error),
+    #[error("protocol version mismatch: local version {local}, remote version {remote}")]
+    Version { local: u32, remote: u32 },
+    #[error("the remote end does not serve the requested Kind")]
+    KindMismatch,
+    #[error("no format in common with the remote end, which supports {supported:?}")]
+    NoCommonFormat { supported: Vec<String> },
+    #[error("the remote end no longer holds the session")]
+    SessionExpired,
+    #[error("securing the connection failed: {0}")]
+    Secure(#[source] Error),
 }
 
 #[derive(Error, Debug, Kind)]
@@ -55,12 +82,86 @@ impl From<TransportError> for ConnectionError {
     }
 }
 
+/// A frame of a network transport.
+#[derive(Kind, Clone, Debug)]
+pub enum Message {
+    Binary(Vec<u8>),
+    Text(String),
+}
+
+impl Message {
+    /// Encodes the message as bytes prefixed with its type, for layers that carry
+    /// messages as binary payloads.
+    fn into_tagged(self) -> Vec<u8> {
+        let (tag, data) = match self {
+            Message::Binary(data) => (0, data),
+            Message::Text(data) => (1, data.into_bytes()),
+        };
+        let mut tagged = Vec::with_capacity(data.len() + 1);
+        tagged.push(tag);
+        tagged.extend_from_slice(&data);
+        tagged
+    }
+    fn from_tagged(mut tagged: Vec<u8>) -> Option<Message> {
+        match tagged.first() {
+            Some(0) => Some(Message::Binary(tagged.split_off(1))),
+            Some(1) => String::from_utf8(tagged.split_off(1))
+                .ok()
+                .map(Message::Text),
+            _ => None,
+        }
+    }
+}
+
+/// A `Format` representation that can be carried by a network transport. Binary
+/// representations are sent as binary frames and textual representations as
+/// text frames, so that a session using a textual format such as `Json` can be
+/// inspected with ordinary websocket tooling.
+pub trait Transported: Clone + Sync + Send + Sized + 'static {
+    fn into_message(self) -> Message;
+    /// Returns `None` if the message is of the wrong type.
+    fn from_message(message: Message) -> Option<Self>;
+}
+
+impl Transported for Vec<u8> {
+    fn into_message(self) -> Message {
+        Message::Binary(self)
+    }
+    fn from_message(message: Message) -> Option<Self> {
+        match message {
+            Message::Binary(data) => Some(data),
+            Message::Text(_) => None,
+        }
+    }
+}
+
+impl Transported for String {
+    fn into_message(self) -> Message {
+        Message::Text(self)
+    }
+    fn from_message(message: Message) -> Option<Self> {
+        match message {
+            Message::Text(data) => Some(data),
+            Message::Binary(_) => None,
+        }
+    }
+}
+
+type Connection = SinkStream<Message, ConnectionError, Message>;
+
+/// Carries the representations of a format over `connection`. A message of the
+/// wrong type ends the connection.
+fn represent<R: Transported>(connection: Connection) -> SinkStream<R, ConnectionError, R> {
+    let (sink, stream) = connection.split();
+    SinkStream::new(
+        sink.with(|item: R| ok::<_, ConnectionError>(item.into_message())),
+        stream.scan((), |_, message| ready(R::from_message(message))),
+    )
+}
+
 #[object]
 pub(crate) trait RawClient {
-    fn connect(
-        &mut self,
-        address: Url,
-    ) -> Fallible<SinkStream<Vec<u8>, ConnectionError, Vec<u8>>, ConnectError>;
+    fn connect(&mut self, address: Url) -> Fallible<Connection, ConnectError>;
 }
 
 #[derive(Kind)]
@@ -70,24 +171,198 @@ impl Client {
     pub fn new() -> Result<Client, UnimplementedError> {
         RawClient::new().map(Client)
     }
-    pub fn connect<
-        'a,
-        K: Kind,
-        T: Target<'a, K> + 'static,
-        F: Format<Representation = Vec<u8>> + 'static,
-    >(
+    /// Connects to a server at `address` and constructs the `Kind` it serves.
+    ///
+    /// The connection begins with a handshake in which the protocol version, the
+    /// expected `Kind` and the format `F` are checked against those of the server,
+    /// so that a mismatch produces a descriptive `ConnectError`.
+    pub fn connect<'a, K: Kind, T: Target<'a, K> + 'static, F: Format + 'static>(
+        &mut self,
+        address: Url,
+    ) -> Fallible<K, ConnectError>
+    where
+        F::Representation: Transported,
+    {
+        let connection = self.0.connect(address);
+        Box::pin(async move { Client::construct::<K, T, F>(connection.await?).await })
+    }
+    /// Connects as `connect` does over a transport secured by `secure` as
+    /// `identity`, returning the authenticated public key of the server along
+    /// with the constructed `Kind`.
+    #[cfg(all(not(target_arch = "wasm32"), feature = "core"))]
+    pub fn connect_secure<'a, K: Kind, T: Target<'a, K> + 'static, F: Format + 'static>(
         &mut self,
         address: Url,
-    ) -> Fallible<K, ConnectError> {
+        identity: Arc<Identity>,
+    ) -> Fallible<(K, PublicKey), ConnectError>
+    where
+        F::Representation: Transported,
+    {
         let connection = self.0.connect(address);
         Box::pin(async move {
-            connection
-                .await?
+            let (peer, connection) =
+                secure(represent(connection.await?), identity, Role::Initiator)
+                    .await
+                    .map_err(|e| ConnectError::Secure(e.into()))?;
+            let kind = Client::construct::<K, T, F>(secured(connection)).await?;
+            Ok((kind, peer))
+        })
+    }
+    fn construct<'a, K: Kind, T: Target<'a, K> + 'static, F: Format + 'static>(
+        connection: Connection,
+    ) -> Fallible<K, ConnectError>
+    where
+        F::Representation: Transported,
+    {
+        Box::pin(async move {
+            let hello = Hello {
+                version: PROTOCOL_VERSION,
+                kind: K::USE_KIND_MACRO_TO_GENERATE_THIS_FIELD,
+                formats: vec![F::name()],
+                resume: Resume::Never,
+            };
+            let (_, connection) = handshake(connection, hello).await?;
+            represent::<F::Representation>(connection)
                 .decode::<T, F>()
                 .await
                 .map_err(|e| ConnectError::Construct(e.into()))
         })
     }
+    /// Connects as `connect` does, but carries the connection over a session that
+    /// can be resumed with `Session::resume` if the underlying transport is lost.
+    /// Up to `capacity` unacknowledged frames are retained for retransmission.
+    pub fn connect_resumable<'a, K: Kind, T: Target<'a, K> + 'static, F: Format + 'static>(
+        &mut self,
+        address: Url,
+        capacity: usize,
+    ) -> Fallible<(K, Session), ConnectError>
+    where
+        F::Representation: Transported,
+    {
+        let connection = self.0.connect(address.clone());
+        Box::pin(async move {
+            let hello = Hello {
+                version: PROTOCOL_VERSION,
+                kind: K::USE_KIND_MACRO_TO_GENERATE_THIS_FIELD,
+                formats: vec![F::name()],
+                resume: Resume::Request,
+            };
+            let (id, transport) = match handshake(connection.await?, hello).await? {
+                (Reply::Session(_, id), transport) => (id, transport),
+                _ => {
+                    return Err(ConnectError::Handshake(anyhow!(
+                        "the remote end does not support resumable sessions"
+                    )))
+                }
+            };
+            let (link, connection) = Link::new(capacity);
+            link.attach(transport, 0);
+            let kind = represent::<F::Representation>(connection)
+                .decode::<T, F>()
+                .await
+                .map_err(|e| ConnectError::Construct(e.into()))?;
+            Ok((
+                kind,
+                Session {
+                    id,
+                    address,
+                    kind: K::USE_KIND_MACRO_TO_GENERATE_THIS_FIELD,
+                    link,
+                },
+            ))
+        })
+    }
+}
+
+/// A resumable connection established by `Client::connect_resumable`.
+///
+/// Forks, remote objects and pending futures built on the connection survive the
+/// loss of its transport so long as the session is resumed within the grace
+/// period of the server.
+pub struct Session {
+    id: Vec<u8>,
+    address: Url,
+    kind: [u8; 32],
+    link: Link,
+}
+
+impl Session {
+    /// Resolves when the transport carrying the session is lost, or immediately
+    /// if it has already been lost.
+    pub fn disconnected(&self) -> Future<()> {
+        self.link.disconnected()
+    }
+    /// Reattaches the session over a new connection to the same address, after
+    /// which any frames lost with the previous transport are retransmitted.
+    ///
+    /// If the server no longer holds the session this fails with
+    /// `ConnectError::SessionExpired` and the session is ended, so that anything
+    /// awaiting the remote end fails rather than waiting indefinitely.
+    pub fn resume(&self, client: &mut Client) -> Fallible<(), ConnectError> {
+        let connection = client.0.connect(self.address.clone());
+        let hello = Hello {
+            version: PROTOCOL_VERSION,
+            kind: self.kind,
+            formats: vec![],
+            resume: Resume::Session(self.id.clone(), self.link.received()),
+        };
+        let link = self.link.clone();
+        Box::pin(async move {
+            match handshake(connection.await?, hello).await {
+                Ok((Reply::Resumed(received), transport)) => {
+                    link.attach(transport, received);
+                    Ok(())
+                }
+                Ok(_) => Err(ConnectError::Handshake(anyhow!(
+                    "the remote end did not resume the session"
+                ))),
+                Err(ConnectError::SessionExpired) => {
+                    link.expire();
+                    Err(ConnectError::SessionExpired)
+                }
+                Err(error) => Err(error),
+            }
+        })
+    }
+}
+
+/// Sends `hello` on `connection` and reads the reply of the server, returning it
+/// along with the connection if it was not a rejection.
+fn handshake(connection: Connection, hello: Hello) -> Fallible<(Reply, Connection), ConnectError> {
+    Box::pin(async move {
+        let (mut sink, mut stream) = connection.split();
+        sink.send(Message::Binary(Cbor::serialize(hello)))
+            .await
+            .map_err(|e| ConnectError::Handshake(e.into()))?;
+        let reply = match stream.next().await {
+            Some(Message::Binary(reply)) => reply,
+            Some(Message::Text(_)) => {
+                return Err(ConnectError::Handshake(anyhow!(
+                    "received a text frame during handshake"
+                )))
+            }
+            None => {
+                return Err(ConnectError::Handshake(anyhow!(
+                    "connection closed during handshake"
+                )))
+            }
+        };
+        let reply = Cbor::deserialize(reply, PhantomData::<Reply>)
+            .await
+            .map_err(|(e, _)| ConnectError::Handshake(e.into()))?;
+        match reply {
+            Reply::Reject(Rejection::Version(remote)) => Err(ConnectError::Version {
+                local: PROTOCOL_VERSION,
+                remote,
+            }),
+            Reply::Reject(Rejection::KindMismatch) => Err(ConnectError::KindMismatch),
+            Reply::Reject(Rejection::NoCommonFormat(supported)) => {
+                Err(ConnectError::NoCommonFormat { supported })
+            }
+            Reply::Reject(Rejection::UnknownSession) => Err(ConnectError::SessionExpired),
+            reply => Ok((reply, SinkStream::new(sink, stream))),
+        }
+    })
 }
 
 #[object]
@@ -95,11 +370,7 @@ pub(crate) trait RawServer {
     fn listen(
         &mut self,
         address: SocketAddr,
-        handler: Box<
-            dyn FnMut(SinkStream<Vec<u8>, ConnectionError, Vec<u8>>) -> Infallible<()>
-                + Sync
-                + Send,
-        >,
+        handler: Box<dyn FnMut(Connection) -> Infallible<()> + Sync + Send>,
     ) -> Fallible<(), ListenError>;
 }
 
@@ -110,12 +381,9 @@ impl Server {
     pub fn new() -> Result<Server, UnimplementedError> {
         RawServer::new().map(Server)
     }
-    pub fn listen<
-        'a,
-        K: Kind,
-        T: Target<'a, K> + 'static,
-        F: Format<Representation = Vec<u8>> + 'static,
-    >(
+    /// Serves the `Kind` produced by `handler` to each client connecting on
+    /// `address`, using the format `F`.
+    pub fn listen<'a, K: Kind, T: Target<'a, K> + 'static, F: Format + 'static>(
         &mut self,
         address: SocketAddr,
         handler: Box<dyn FnMut() -> Future<K> + Sync + Send>,
@@ -123,22 +391,154 @@ impl Server {
     where
         T: ApplyEncode<'a>,
         <T as Sink<<T as Context<'a>>::Item>>::Error: std::error::Error + Sync + Send + 'static,
+        F::Representation: Transported,
     {
+        self.listen_with(address, Formats::new().with::<T, F>(), handler)
+    }
+    /// Serves the `Kind` produced by `handler` to each client connecting on
+    /// `address`, using whichever of `formats` the client prefers.
+    ///
+    /// Clients whose protocol version, expected `Kind` or formats do not match are
+    /// refused during the handshake and `handler` is not invoked for them.
+    pub fn listen_with<K: Kind>(
+        &mut self,
+        address: SocketAddr,
+        formats: Formats<K>,
+        handler: Box<dyn FnMut() -> Future<K> + Sync + Send>,
+    ) -> Fallible<(), ListenError> {
+        self.accept(address, formats, None, Arc::new(plain), unit(handler))
+    }
+    /// Serves as `listen_with` does, but offers clients resumable sessions that are
+    /// retained according to `resumption` after their transport is lost.
+    pub fn listen_resumable<K: Kind>(
+        &mut self,
+        address: SocketAddr,
+        formats: Formats<K>,
+        resumption: Resumption,
+        handler: Box<dyn FnMut() -> Future<K> + Sync + Send>,
+    ) -> Fallible<(), ListenError> {
+        self.accept(
+            address,
+            formats,
+            Some(Sessions::new(resumption)),
+            Arc::new(plain),
+            unit(handler),
+        )
+    }
+    /// Serves as `listen_with` does over transports secured by `secure` as
+    /// `identity`, passing the authenticated public key of each client to
+    /// `handler`.
+    #[cfg(all(not(target_arch = "wasm32"), feature = "core"))]
+    pub fn listen_secure<K: Kind>(
+        &mut self,
+        address: SocketAddr,
+        formats: Formats<K>,
+        identity: Arc<Identity>,
+        handler: Box<dyn FnMut(PublicKey) -> Future<K> + Sync + Send>,
+    ) -> Fallible<(), ListenError> {
+        self.accept(
+            address,
+            formats,
+            None,
+            Arc::new(
+                move |connection| -> Fallible<(PublicKey, Connection), ConnectionError> {
+                    let identity = identity.clone();
+                    Box::pin(async move {
+                        let (peer, connection) =
+                            secure(represent(connection), identity, Role::Responder)
+                                .await
+                                .map_err(|e| ConnectionError { cause: e.into() })?;
+                        Ok((peer, secured(connection)))
+                    })
+                },
+            ),
+            handler,
+        )
+    }
+    fn accept<K: Kind, P: Sync + Send + 'static>(
+        &mut self,
+        address: SocketAddr,
+        formats: Formats<K>,
+        sessions: Option<Sessions>,
+        upgrade: Upgrade<P>,
+        handler: Box<dyn FnMut(P) -> Future<K> + Sync + Send>,
+    ) -> Fallible<(), ListenError> {
         let handler = Arc::new(Mutex::new(handler));
+        let formats = Arc::new(formats);
         self.0.listen(
             address,
             Box::new(move |channel| {
                 let handler = handler.clone();
+                let formats = formats.clone();
+                let sessions = sessions.clone();
+                let upgrade = upgrade.clone();
                 Box::pin(async move {
-                    let (sender, receiver) = channel.split();
-                    let (sink, stream) = (handler.lock().await.as_mut())()
+                    let (peer, channel) = match upgrade(channel).await {
+                        Ok(upgraded) => upgraded,
+                        Err(_) => return Ok(()),
+                    };
+                    let (mut sender, mut receiver) = channel.split();
+                    let hello = match receiver.next().await {
+                        Some(Message::Binary(hello)) => {
+                            Cbor::deserialize(hello, PhantomData::<Hello>).await
+                        }
+                        _ => return Ok(()),
+                    };
+                    let hello = match hello {
+                        Ok(hello) => hello,
+                        Err(_) => return Ok(()),
+                    };
+                    let reply = match hello.check(K::USE_KIND_MACRO_TO_GENERATE_THIS_FIELD) {
+                        Err(rejection) => Reply::Reject(rejection),
+                        Ok(()) => match (&hello.resume, &sessions) {
+                            (Resume::Session(id, _), Some(sessions)) => sessions
+                                .received(id)
+                                .map_or(Reply::Reject(Rejection::UnknownSession), Reply::Resumed),
+                            (Resume::Session(..), None) => Reply::Reject(Rejection::UnknownSession),
+                            _ => hello
+                                .choose(&formats.names())
+                                .map_or_else(Reply::Reject, Reply::Accept),
+                        },
+                    };
+                    let (reply, session) = match (reply, &hello.resume, &sessions) {
+                        (Reply::Accept(name), Resume::Request, Some(sessions)) => {
+                            match sessions.create().await {
+                                Some((id, connection)) => {
+                                    (Reply::Session(name, id), Some(connection))
+                                }
+                                None => (Reply::Accept(name), None),
+                            }
+                        }
+                        (reply, _, _) => (reply, None),
+                    };
+                    if sender
+                        .send(Message::Binary(Cbor::serialize(&reply)))
                         .await
-                        .on_to::<T>()
-                        .await
-                        .encode::<F>()
-                        .split();
-                    spawn(stream.map(Ok).forward(sender).then(|_| ready(())));
-                    spawn(receiver.map(Ok).forward(sink).then(|_| ready(())));
+                        .is_err()
+                    {
+                        return Ok(());
+                    }
+                    let transport = SinkStream::new(sender, receiver);
+                    match (reply, session, &sessions) {
+                        (Reply::Accept(name), _, _) => {
+                            if let Some(serve) = formats.get(&name) {
+                                serve((handler.lock().await.as_mut())(peer).await, transport).await;
+                            }
+                        }
+                        (Reply::Session(name, id), Some(connection), Some(sessions)) => {
+                            sessions.attach(&id, transport, 0);
+                            if let Some(serve) = formats.get(&name) {
+                                serve((handler.lock().await.as_mut())(peer).await, connection)
+                                    .await;
+                            }
+                        }
+                        (Reply::Resumed(_), _, Some(sessions)) => {
+                            if let Resume::Session(id, received) = &hello.resume {
+                                sessions.attach(id, transport, *received);
+                            }
+                        }
+                        _ => {}
+                    }
                     Ok(())
                 })
             }),
@@ -146,6 +546,87 @@ impl Server {
     }
 }
 
+type Upgrade<P> =
+    Arc<dyn Fn(Connection) -> Fallible<(P, Connection), ConnectionError> + Sync + Send>;
+
+fn plain(connection: Connection) -> Fallible<((), Connection), ConnectionError> {
+    Box::pin(ready(Ok(((), connection))))
+}
+
+fn unit<K>(
+    mut handler: Box<dyn FnMut() -> Future<K> + Sync + Send>,
+) -> Box<dyn FnMut(()) -> Future<K> + Sync + Send> {
+    Box::new(move |()| handler())
+}
+
+#[cfg(all(not(target_arch = "wasm32"), feature = "core"))]
+fn secured(connection: SinkStream<Vec<u8>, SecureError, Vec<u8>>) -> Connection {
+    let (sink, stream) = connection.split();
+    SinkStream::new(
+        sink.sink_map_err(|e| ConnectionError { cause: e.into() })
+            .with(|message: Message| ok::<_, ConnectionError>(message.into_tagged())),
+        stream.scan((), |_, message| ready(Message::from_tagged(message))),
+    )
+}
+
+type Serve<K> = fn(K, Connection) -> Future<()>;
+
+/// The formats a `Server` offers for a `Kind`, in order of registration.
+pub struct Formats<K: Kind> {
+    formats: Vec<(String, Serve<K>)>,
+}
+
+impl<K: Kind> Default for Formats<K> {
+    fn default() -> Self {
+        Formats::new()
+    }
+}
+
+impl<K: Kind> Formats<K> {
+    pub fn new() -> Self {
+        Formats { formats: vec![] }
+    }
+    /// Offers the format `F`, transporting the `Kind` over the `Target` `T`.
+    pub fn with<'a, T: Target<'a, K> + 'static, F: Format + 'static>(mut self) -> Self
+    where
+        T: ApplyEncode<'a>,
+        <T as Sink<<T as Context<'a>>::Item>>::Error: std::error::Error + Sync + Send + 'static,
+        F::Representation: Transported,
+    {
+        let name = F::name();
+        if !self.formats.iter().any(|(existing, _)| existing == &name) {
+            self.formats.push((name, serve::<K, T, F>));
+        }
+        self
+    }
+    fn names(&self) -> Vec<String> {
+        self.formats.iter().map(|(name, _)| name.clone()).collect()
+    }
+    fn get(&self, name: &str) -> Option<Serve<K>> {
+        self.formats
+            .iter()
+            .find(|(existing, _)| existing == name)
+            .map(|(_, serve)| *serve)
+    }
+}
+
+fn serve<'a, K: Kind, T: Target<'a, K> + 'static, F: Format + 'static>(
+    kind: K,
+    connection: Connection,
+) -> Future<()>
+where
+    T: ApplyEncode<'a>,
+    <T as Sink<<T as Context<'a>>::Item>>::Error: std::error::Error + Sync + Send + 'static,
+    F::Representation: Transported,
+{
+    Box::pin(async move {
+        let (sender, receiver) = represent::<F::Representation>(connection).split();
+        let (sink, stream) = kind.on_to::<T>().await.encode::<F>().split();
+        spawn(stream.map(Ok).forward(sender).then(|_| ready(())));
+        spawn(receiver.map(Ok).forward(sink).then(|_| ready(())));
+    })
+}
+
 #[cfg(all(not(target_arch = "wasm32"), feature = "core"))]
 mod native;
 #[cfg(all(target_arch = "wasm32", feature = "core"))]
@@ -178,3 +659,202 @@ impl dyn RawServer {
         });
     }
 }
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::{channel::IdChannel, format::Batched};
+
+    use futures::{
+        channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender},
+        executor::block_on,
+    };
+    use std::sync::Mutex as SyncMutex;
+
+    type Handler = Box<dyn FnMut(Connection) -> Infallible<()> + Sync + Send>;
+
+    /// An in-process transport, over which a `Client` connects to the `Server`
+    /// listening on the same `Loopback`. Connecting while no server is listening
+    /// yields a connection that is closed immediately.
+    #[derive(Clone, Default)]
+    struct Loopback(Arc<SyncMutex<Option<Handler>>>);
+
+    impl RawServer for Loopback {
+        fn listen(&mut self, _: SocketAddr, handler: Handler) -> Fallible<(), ListenError> {
+            *self.0.lock().unwrap() = Some(handler);
+            Box::pin(ready(Ok(())))
+        }
+    }
+
+    impl RawClient for Loopback {
+        fn connect(&mut self, _: Url) -> Fallible<Connection, ConnectError> {
+            let (to_server, from_client) = unbounded();
+            let (to_client, from_server) = unbounded();
+            if let Some(handler) = self.0.lock().unwrap().as_mut() {
+                spawn(handler(end(to_client, from_client)).map(|_| ()));
+            }
+            Box::pin(ready(Ok(end(to_server, from_server))))
+        }
+    }
+
+    fn end(sender: UnboundedSender<Message>, receiver: UnboundedReceiver<Message>) -> Connection {
+        SinkStream::new(
+            sender.sink_map_err(|e| ConnectionError { cause: e.into() }),
+            receiver,
+        )
+    }
+
+    fn address() -> (SocketAddr, Url) {
+        (
+            "127.0.0.1:0".parse().unwrap(),
+            Url::parse("ws://127.0.0.1/").unwrap(),
+        )
+    }
+
+    fn served() -> Box<dyn FnMut() -> Future<String> + Sync + Send> {
+        Box::new(|| Box::pin(ready("served".to_owned())) as Future<String>)
+    }
+
+    /// Listens with `formats` and the plain handler on a new `Loopback`,
+    /// returning a `Client` connecting to it.
+    fn listen(formats: Formats<String>) -> Client {
+        let loopback = Loopback::default();
+        let (address, _) = address();
+        let _ = Server(Box::new(loopback.clone())).listen_with(address, formats, served());
+        Client(Box::new(loopback))
+    }
+
+    fn hello(version: u32, formats: &[&str], resume: Resume) -> Hello {
+        Hello {
+            version,
+            kind: String::USE_KIND_MACRO_TO_GENERATE_THIS_FIELD,
+            formats: formats.iter().map(|format| format.to_string()).collect(),
+            resume,
+        }
+    }
+
+    /// Performs the handshake of the client, sending `hello`, against the server
+    /// listening through `client`.
+    fn negotiate(client: &mut Client, hello: Hello) -> Result<Reply, ConnectError> {
+        let (_, url) = address();
+        block_on(async {
+            let connection = client.0.connect(url).await?;
+            handshake(connection, hello).await.map(|(reply, _)| reply)
+        })
+    }
+
+    #[test]
+    fn each_offered_format_is_served() {
+        let formats = || {
+            Formats::new()
+                .with::<IdChannel, Cbor>()
+                .with::<IdChannel, Batched<Cbor>>()
+        };
+        let (_, url) = address();
+        let mut client = listen(formats());
+        let value = block_on(client.connect::<String, IdChannel, Cbor>(url.clone())).unwrap();
+        assert_eq!(value, "served");
+        let mut client = listen(formats());
+        let value = block_on(client.connect::<String, IdChannel, Batched<Cbor>>(url)).unwrap();
+        assert_eq!(value, "served");
+    }
+
+    #[test]
+    fn preferred_format_is_accepted() {
+        let mut client = listen(
+            Formats::new()
+                .with::<IdChannel, Cbor>()
+                .with::<IdChannel, Batched<Cbor>>(),
+        );
+        let hello = hello(
+            PROTOCOL_VERSION,
+            &[&Batched::<Cbor>::name(), &Cbor::name()],
+            Resume::Never,
+        );
+        match negotiate(&mut client, hello) {
+            Ok(Reply::Accept(name)) => assert_eq!(name, Batched::<Cbor>::name()),
+            _ => panic!("the handshake was not accepted"),
+        }
+    }
+
+    #[test]
+    fn version_mismatch_fails_connect() {
+        let mut client = listen(Formats::new().with::<IdChannel, Cbor>());
+        let hello = hello(PROTOCOL_VERSION + 1, &[&Cbor::name()], Resume::Never);
+        match negotiate(&mut client, hello) {
+            Err(ConnectError::Version { local, remote }) => {
+                assert_eq!(local, PROTOCOL_VERSION);
+                assert_eq!(remote, PROTOCOL_VERSION);
+            }
+            _ => panic!("a version mismatch did not fail the handshake"),
+        }
+    }
+
+    #[test]
+    fn kind_mismatch_fails_connect() {
+        let (_, url) = address();
+        let mut client = listen(Formats::new().with::<IdChannel, Cbor>());
+        match block_on(client.connect::<u32, IdChannel, Cbor>(url)) {
+            Err(ConnectError::KindMismatch) => {}
+            _ => panic!("a Kind mismatch did not fail the handshake"),
+        }
+    }
+
+    #[test]
+    fn no_common_format_fails_connect() {
+        let (_, url) = address();
+        let mut client = listen(Formats::new().with::<IdChannel, Cbor>());
+        match block_on(client.connect::<String, IdChannel, Batched<Cbor>>(url)) {
+            Err(ConnectError::NoCommonFormat { supported }) => {
+                assert_eq!(supported, vec![Cbor::name()])
+            }
+            _ => panic!("a lack of common formats did not fail the handshake"),
+        }
+    }
+
+    #[test]
+    fn closed_connection_fails_connect() {
+        let (_, url) = address();
+        let mut client = Client(Box::new(Loopback::default()));
+        match block_on(client.connect::<String, IdChannel, Cbor>(url)) {
+            Err(ConnectError::Handshake(_)) => {}
+            _ => panic!("a closed connection did not fail the handshake"),
+        }
+    }
+
+    #[test]
+    fn sessions_require_a_resumable_server() {
+        let (_, url) = address();
+        let mut client = listen(Formats::new().with::<IdChannel, Cbor>());
+        match block_on(client.connect_resumable::<String, IdChannel, Cbor>(url, 16)) {
+            Err(ConnectError::Handshake(_)) => {}
+            _ => panic!("a session was established with a server that cannot resume it"),
+        }
+        let hello = hello(PROTOCOL_VERSION, &[], Resume::Session(vec![0; 16], 0));
+        match negotiate(&mut client, hello) {
+            Err(ConnectError::SessionExpired) => {}
+            _ => panic!("a session was resumed by a server that cannot resume it"),
+        }
+    }
+
+    #[test]
+    fn resumable_servers_establish_sessions() {
+        let loopback = Loopback::default();
+        let (address, url) = address();
+        let _ = Server(Box::new(loopback.clone())).listen_resumable(
+            address,
+            Formats::new().with::<IdChannel, Cbor>(),
+            Resumption::default(),
+            served(),
+        );
+        let mut client = Client(Box::new(loopback));
+        let (value, _session) =
+            block_on(client.connect_resumable::<String, IdChannel, Cbor>(url, 16)).unwrap();
+        assert_eq!(value, "served");
+        let hello = hello(PROTOCOL_VERSION, &[], Resume::Session(vec![0; 16], 0));
+        match negotiate(&mut client, hello) {
+            Err(ConnectError::SessionExpired) => {}
+            _ => panic!("an unknown session was resumed"),
+        }
+    }
+}
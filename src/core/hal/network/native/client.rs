@@ -1,4 +1,4 @@
-use super::super::{ConnectError, ConnectionError, RawClient};
+use super::super::{ConnectError, ConnectionError, Message, RawClient};
 
 use crate::{
     core::spawn,
@@ -15,7 +15,7 @@ use futures::{
 };
 use std::sync::{self, Arc};
 use url::Url;
-use ws::{connect, Message};
+use ws::{connect, Message as WsMessage};
 
 pub(crate) struct Client;
 
@@ -23,9 +23,9 @@ impl RawClient for Client {
     fn connect(
         &mut self,
         address: Url,
-    ) -> Fallible<SinkStream<Vec<u8>, ConnectionError, Vec<u8>>, ConnectError> {
+    ) -> Fallible<SinkStream<Message, ConnectionError, Message>, ConnectError> {
         Box::pin(async move {
-            let (out_sender, out_receiver): (_, UnboundedReceiver<Vec<u8>>) = unbounded();
+            let (out_sender, out_receiver): (_, UnboundedReceiver<Message>) = unbounded();
             let out_receiver = Arc::new(Mutex::new(out_receiver));
             let (data_sender, data_receiver) = unbounded();
             let (sender, receiver) = channel();
@@ -37,13 +37,20 @@ impl RawClient for Client {
                     let out_receiver = out_receiver.clone();
                     spawn(async move {
                         while let Some(item) = out_receiver.lock().await.next().await {
-                            peer.send(item).unwrap();
+                            peer.send(match item {
+                                Message::Binary(data) => WsMessage::Binary(data),
+                                Message::Text(data) => WsMessage::Text(data),
+                            })
+                            .unwrap();
                         }
                     });
                     move |message| {
-                        if let Message::Binary(data) = message {
-                            data_sender.unbounded_send(data).unwrap();
-                        }
+                        data_sender
+                            .unbounded_send(match message {
+                                WsMessage::Binary(data) => Message::Binary(data),
+                                WsMessage::Text(data) => Message::Text(data),
+                            })
+                            .unwrap();
                         Ok(())
                     }
                 })
@@ -1,4 +1,4 @@
-use super::super::{ConnectionError, ListenError, RawServer};
+use super::super::{ConnectionError, ListenError, Message, RawServer};
 
 use crate::{
     core::spawn,
@@ -7,7 +7,7 @@ use crate::{
 
 use futures::{channel::mpsc::unbounded, lock::Mutex, SinkExt, StreamExt};
 use std::{net::SocketAddr, sync::Arc};
-use ws::{listen, Message};
+use ws::{listen, Message as WsMessage};
 
 pub(crate) struct Server;
 
@@ -16,7 +16,7 @@ impl RawServer for Server {
         &mut self,
         address: SocketAddr,
         handler: Box<
-            dyn FnMut(SinkStream<Vec<u8>, ConnectionError, Vec<u8>>) -> Infallible<()>
+            dyn FnMut(SinkStream<Message, ConnectionError, Message>) -> Infallible<()>
                 + Sync
                 + Send,
         >,
@@ -30,7 +30,11 @@ impl RawServer for Server {
                     let (data_sender, mut stream) = unbounded();
                     spawn(async move {
                         while let Some(item) = stream.next().await {
-                            peer.send(item).unwrap();
+                            peer.send(match item {
+                                Message::Binary(data) => WsMessage::Binary(data),
+                                Message::Text(data) => WsMessage::Text(data),
+                            })
+                            .unwrap();
                         }
                     });
                     (handler.lock().await.as_mut())(SinkStream::new(
@@ -41,9 +45,12 @@ impl RawServer for Server {
                     .unwrap();
                 });
                 move |message| {
-                    if let Message::Binary(data) = message {
-                        sender.unbounded_send(data).unwrap();
-                    }
+                    sender
+                        .unbounded_send(match message {
+                            WsMessage::Binary(data) => Message::Binary(data),
+                            WsMessage::Text(data) => Message::Text(data),
+                        })
+                        .unwrap();
                     Ok(())
                 }
             })
@@ -0,0 +1,372 @@
+//! Authenticated encryption of byte transports.
+//!
+//! [`secure`](fn.secure.html) wraps any transport of byte frames. One end acts
+//! as the initiator and the other as the responder. Both ends exchange ephemeral
+//! X25519 keys along with their long-term Ed25519
+//! [`Identity`](struct.Identity.html), then each signs the transcript of both
+//! ephemeral and identity keys, ordered by role, together with the role in
+//! which it signs. A relay that presents its own identity to either end thus
+//! fails to authenticate to the other. A key for each direction is derived
+//! using HKDF salted with the transcript. Every subsequent frame is sealed with
+//! ChaCha20-Poly1305 under a nonce taken from a per-direction counter, so a
+//! replayed, reordered or altered frame fails to open and ends the stream.
+
+use crate::kind::{Fallible, SinkStream};
+
+use anyhow::Error;
+use core::pin::Pin;
+use futures::{
+    future::ready,
+    task::{Context, Poll},
+    Sink, SinkExt, StreamExt,
+};
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305},
+    agreement::{self, agree_ephemeral, EphemeralPrivateKey, X25519},
+    digest::{digest, Digest, SHA256},
+    hkdf::{Salt, HKDF_SHA256},
+    rand::SystemRandom,
+    signature::{self, Ed25519KeyPair, KeyPair, ED25519},
+};
+use std::sync::Arc;
+use thiserror::Error;
+
+const TRANSCRIPT_LABEL: &[u8] = b"vessels secure transport v2";
+const INITIATOR_TO_RESPONDER: &[u8] = b"initiator to responder";
+const RESPONDER_TO_INITIATOR: &[u8] = b"responder to initiator";
+
+#[derive(Error, Debug)]
+pub enum SecureError {
+    #[error("underlying transport failed: {0}")]
+    Transport(#[source] Error),
+    #[error("transport closed during the handshake")]
+    Closed,
+    #[error("malformed handshake message")]
+    Malformed,
+    #[error("the remote end failed to authenticate")]
+    Authentication,
+    #[error("cryptographic operation failed")]
+    Crypto,
+}
+
+/// The public half of an `Identity`, as presented by an authenticated peer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PublicKey(pub [u8; 32]);
+
+/// A long-term Ed25519 key pair with which an end of a transport authenticates
+/// itself.
+pub struct Identity(Ed25519KeyPair);
+
+impl Identity {
+    /// Generates a new identity, returning it along with its PKCS#8 encoding so
+    /// that it can be stored and later restored with `from_pkcs8`.
+    pub fn generate() -> Result<(Identity, Vec<u8>), SecureError> {
+        let document = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+            .map_err(|_| SecureError::Crypto)?;
+        let pkcs8 = document.as_ref().to_vec();
+        Ok((Identity::from_pkcs8(&pkcs8)?, pkcs8))
+    }
+    pub fn from_pkcs8(pkcs8: &[u8]) -> Result<Identity, SecureError> {
+        Ed25519KeyPair::from_pkcs8(pkcs8)
+            .map(Identity)
+            .map_err(|_| SecureError::Crypto)
+    }
+    pub fn public_key(&self) -> PublicKey {
+        let mut key = [0u8; 32];
+        key.copy_from_slice(self.0.public_key().as_ref());
+        PublicKey(key)
+    }
+}
+
+/// The part an end plays in the handshake. The two ends of a transport must take
+/// different roles.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    Initiator,
+    Responder,
+}
+
+impl Role {
+    fn label(self) -> &'static [u8] {
+        match self {
+            Role::Initiator => b"initiator",
+            Role::Responder => b"responder",
+        }
+    }
+    fn peer(self) -> Role {
+        match self {
+            Role::Initiator => Role::Responder,
+            Role::Responder => Role::Initiator,
+        }
+    }
+}
+
+/// Hashes the hellos of both ends, each an ephemeral key followed by an identity
+/// key, in the order of their roles.
+fn transcript(initiator: &[u8], responder: &[u8]) -> Digest {
+    let mut transcript = TRANSCRIPT_LABEL.to_vec();
+    transcript.extend_from_slice(initiator);
+    transcript.extend_from_slice(responder);
+    digest(&SHA256, &transcript)
+}
+
+/// The message signed by the end acting as `role`.
+fn signed(role: Role, transcript: &Digest) -> Vec<u8> {
+    let mut signed = role.label().to_vec();
+    signed.extend_from_slice(transcript.as_ref());
+    signed
+}
+
+/// Performs the key agreement handshake over `transport` as `identity` acting as
+/// `role`, returning the authenticated public key of the remote end and an
+/// encrypted transport.
+pub fn secure<E: std::error::Error + Sync + Send + 'static>(
+    transport: SinkStream<Vec<u8>, E, Vec<u8>>,
+    identity: Arc<Identity>,
+    role: Role,
+) -> Fallible<(PublicKey, SinkStream<Vec<u8>, SecureError, Vec<u8>>), SecureError> {
+    Box::pin(async move {
+        let (mut sink, mut stream) = transport.split();
+        let ephemeral = EphemeralPrivateKey::generate(&X25519, &SystemRandom::new())
+            .map_err(|_| SecureError::Crypto)?;
+        let local = ephemeral
+            .compute_public_key()
+            .map_err(|_| SecureError::Crypto)?;
+        let local = local.as_ref().to_vec();
+
+        let mut hello = local.clone();
+        hello.extend_from_slice(identity.0.public_key().as_ref());
+        sink.send(hello.clone())
+            .await
+            .map_err(|e| SecureError::Transport(e.into()))?;
+        let remote_hello = stream.next().await.ok_or(SecureError::Closed)?;
+        if remote_hello.len() != 64 {
+            return Err(SecureError::Malformed);
+        }
+        let (remote, peer) = remote_hello.split_at(32);
+        if remote == local.as_slice() {
+            return Err(SecureError::Authentication);
+        }
+
+        let transcript = match role {
+            Role::Initiator => transcript(&hello, &remote_hello),
+            Role::Responder => transcript(&remote_hello, &hello),
+        };
+
+        sink.send(
+            identity
+                .0
+                .sign(&signed(role, &transcript))
+                .as_ref()
+                .to_vec(),
+        )
+        .await
+        .map_err(|e| SecureError::Transport(e.into()))?;
+        let signature = stream.next().await.ok_or(SecureError::Closed)?;
+        signature::UnparsedPublicKey::new(&ED25519, peer)
+            .verify(&signed(role.peer(), &transcript), &signature)
+            .map_err(|_| SecureError::Authentication)?;
+
+        let (sealing, opening) = agree_ephemeral(
+            ephemeral,
+            &agreement::UnparsedPublicKey::new(&X25519, remote),
+            SecureError::Crypto,
+            |secret| {
+                let key = Salt::new(HKDF_SHA256, transcript.as_ref()).extract(secret);
+                let derive = |info: &[u8]| {
+                    key.expand(&[info], &CHACHA20_POLY1305)
+                        .map(|key| LessSafeKey::new(UnboundKey::from(key)))
+                        .map_err(|_| SecureError::Crypto)
+                };
+                let (outbound, inbound) = (
+                    derive(INITIATOR_TO_RESPONDER)?,
+                    derive(RESPONDER_TO_INITIATOR)?,
+                );
+                Ok(match role {
+                    Role::Initiator => (outbound, inbound),
+                    Role::Responder => (inbound, outbound),
+                })
+            },
+        )?;
+
+        let mut key = [0u8; 32];
+        key.copy_from_slice(peer);
+        Ok((
+            PublicKey(key),
+            SinkStream::new(
+                Seal {
+                    sink,
+                    key: sealing,
+                    counter: 0,
+                },
+                stream.scan((opening, 0u64), |(key, counter), frame| {
+                    ready(open(key, counter, frame))
+                }),
+            ),
+        ))
+    })
+}
+
+fn nonce(counter: u64) -> Nonce {
+    let mut nonce = [0u8; 12];
+    nonce[4..].copy_from_slice(&counter.to_be_bytes());
+    Nonce::assume_unique_for_key(nonce)
+}
+
+fn open(key: &LessSafeKey, counter: &mut u64, mut frame: Vec<u8>) -> Option<Vec<u8>> {
+    let len = key
+        .open_in_place(nonce(*counter), Aad::empty(), &mut frame)
+        .ok()?
+        .len();
+    *counter += 1;
+    frame.truncate(len);
+    Some(frame)
+}
+
+struct Seal<S> {
+    sink: S,
+    key: LessSafeKey,
+    counter: u64,
+}
+
+impl<S: Sink<Vec<u8>> + Unpin> Sink<Vec<u8>> for Seal<S>
+where
+    S::Error: std::error::Error + Sync + Send + 'static,
+{
+    type Error = SecureError;
+
+    fn start_send(mut self: Pin<&mut Self>, mut item: Vec<u8>) -> Result<(), Self::Error> {
+        let this = &mut *self;
+        this.key
+            .seal_in_place_append_tag(nonce(this.counter), Aad::empty(), &mut item)
+            .map_err(|_| SecureError::Crypto)?;
+        this.counter += 1;
+        this.sink
+            .start_send_unpin(item)
+            .map_err(|e| SecureError::Transport(e.into()))
+    }
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.sink
+            .poll_ready_unpin(cx)
+            .map_err(|e| SecureError::Transport(e.into()))
+    }
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.sink
+            .poll_flush_unpin(cx)
+            .map_err(|e| SecureError::Transport(e.into()))
+    }
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.sink
+            .poll_close_unpin(cx)
+            .map_err(|e| SecureError::Transport(e.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::{
+        channel::mpsc::{unbounded, SendError},
+        executor::block_on,
+        future::{join, join3},
+    };
+
+    type Transport = SinkStream<Vec<u8>, SendError, Vec<u8>>;
+
+    fn pipe() -> (Transport, Transport) {
+        let (near, from_near) = unbounded();
+        let (far, from_far) = unbounded();
+        (
+            SinkStream::new(near, from_far),
+            SinkStream::new(far, from_near),
+        )
+    }
+
+    fn identity() -> Arc<Identity> {
+        Arc::new(Identity::generate().unwrap().0)
+    }
+
+    #[test]
+    fn secured_transport_carries_frames() {
+        block_on(async {
+            let (client, server) = (identity(), identity());
+            let (near, far) = pipe();
+            let (initiator, responder) = join(
+                secure(near, client.clone(), Role::Initiator),
+                secure(far, server.clone(), Role::Responder),
+            )
+            .await;
+            let (peer, mut near) = initiator.ok().unwrap();
+            assert_eq!(peer, server.public_key());
+            let (peer, mut far) = responder.ok().unwrap();
+            assert_eq!(peer, client.public_key());
+            near.send(vec![1, 2, 3]).await.ok().unwrap();
+            assert_eq!(far.next().await, Some(vec![1, 2, 3]));
+            far.send(vec![4, 5, 6]).await.ok().unwrap();
+            assert_eq!(near.next().await, Some(vec![4, 5, 6]));
+        });
+    }
+
+    #[test]
+    fn ends_in_the_same_role_fail_to_authenticate() {
+        block_on(async {
+            let (near, far) = pipe();
+            let (near, far) = join(
+                secure(near, identity(), Role::Initiator),
+                secure(far, identity(), Role::Initiator),
+            )
+            .await;
+            match (near, far) {
+                (Err(SecureError::Authentication), Err(SecureError::Authentication)) => {}
+                _ => panic!("ends in the same role authenticated each other"),
+            }
+        });
+    }
+
+    #[test]
+    fn relay_substituting_its_identity_is_rejected() {
+        block_on(async {
+            let (client, server, relay) = (identity(), identity(), identity());
+            let (near, mut to_client) = pipe();
+            let (far, mut to_server) = pipe();
+            // passes the ephemeral keys through but presents its own identity
+            // to the server in place of the client's
+            let relaying = {
+                let relay = relay.clone();
+                async move {
+                    let mut hello = to_client.next().await.unwrap();
+                    hello[32..].copy_from_slice(&relay.public_key().0);
+                    to_server.send(hello.clone()).await.ok().unwrap();
+                    let reply = to_server.next().await.unwrap();
+                    to_client.send(reply.clone()).await.ok().unwrap();
+                    to_client.next().await.unwrap();
+                    let signature = relay
+                        .0
+                        .sign(&signed(Role::Initiator, &transcript(&hello, &reply)));
+                    to_server
+                        .send(signature.as_ref().to_vec())
+                        .await
+                        .ok()
+                        .unwrap();
+                    let signature = to_server.next().await.unwrap();
+                    to_client.send(signature).await.ok().unwrap();
+                }
+            };
+            let (initiator, responder, _) = join3(
+                secure(near, client, Role::Initiator),
+                secure(far, server, Role::Responder),
+                relaying,
+            )
+            .await;
+            match initiator {
+                Err(SecureError::Authentication) => {}
+                _ => panic!("the client accepted a transcript altered by the relay"),
+            }
+            match responder {
+                Ok((peer, _)) => assert_eq!(peer, relay.public_key()),
+                Err(_) => panic!("the relay failed to authenticate as itself"),
+            }
+        });
+    }
+}
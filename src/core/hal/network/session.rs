@@ -0,0 +1,470 @@
+use super::{Connection, ConnectionError, Message};
+
+use crate::{
+    core::{
+        hal::{crypto::Rng, time::Clock},
+        spawn,
+    },
+    kind::{Future, SinkStream},
+};
+
+use core::{
+    convert::TryInto,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+use futures::{
+    channel::{
+        mpsc::{unbounded, UnboundedSender},
+        oneshot,
+    },
+    FutureExt, Sink, StreamExt,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+const DATA: u8 = 0;
+const ACK: u8 = 1;
+
+/// A frame of the session layer, carried as a binary message. Frames are laid out
+/// as a tag byte followed by a big-endian sequence number and, for data frames,
+/// the tagged encoding of the message carried.
+enum Envelope {
+    Data(u64, Vec<u8>),
+    Ack(u64),
+}
+
+impl Envelope {
+    fn data(sequence: u64, payload: &[u8]) -> Message {
+        let mut frame = Vec::with_capacity(payload.len() + 9);
+        frame.push(DATA);
+        frame.extend_from_slice(&sequence.to_be_bytes());
+        frame.extend_from_slice(payload);
+        Message::Binary(frame)
+    }
+    fn ack(sequence: u64) -> Message {
+        let mut frame = vec![ACK];
+        frame.extend_from_slice(&sequence.to_be_bytes());
+        Message::Binary(frame)
+    }
+    fn parse(frame: Message) -> Option<Envelope> {
+        let mut frame = match frame {
+            Message::Binary(frame) => frame,
+            Message::Text(_) => return None,
+        };
+        if frame.len() < 9 {
+            return None;
+        }
+        let sequence = u64::from_be_bytes(frame[1..9].try_into().unwrap());
+        match frame[0] {
+            DATA => Some(Envelope::Data(sequence, frame.split_off(9))),
+            ACK => Some(Envelope::Ack(sequence)),
+            _ => None,
+        }
+    }
+}
+
+/// The configuration of resumable sessions.
+#[derive(Clone, Debug)]
+pub struct Resumption {
+    /// How long the server retains a session after its transport is lost.
+    pub grace: Duration,
+    /// The number of unacknowledged frames each end retains for retransmission.
+    /// Sending blocks while this many frames are unacknowledged.
+    pub capacity: usize,
+}
+
+impl Default for Resumption {
+    fn default() -> Self {
+        Resumption {
+            grace: Duration::from_secs(30),
+            capacity: 1024,
+        }
+    }
+}
+
+struct State {
+    sent: u64,
+    unacked: VecDeque<(u64, Vec<u8>)>,
+    capacity: usize,
+    received: u64,
+    inbound: Option<UnboundedSender<Message>>,
+    outbound: Option<UnboundedSender<Message>>,
+    attachment: u64,
+    writer: Option<Waker>,
+    detached: Vec<oneshot::Sender<()>>,
+}
+
+impl State {
+    fn acknowledge(&mut self, sequence: u64) {
+        while self
+            .unacked
+            .front()
+            .map_or(false, |(sent, _)| *sent <= sequence)
+        {
+            self.unacked.pop_front();
+        }
+        if self.unacked.len() < self.capacity {
+            if let Some(writer) = self.writer.take() {
+                writer.wake();
+            }
+        }
+    }
+    fn detach(&mut self) {
+        self.outbound = None;
+        for notify in self.detached.drain(..) {
+            let _ = notify.send(());
+        }
+    }
+}
+
+/// The local end of a session, which sequences the frames sent over a
+/// succession of transports so that a replacement transport resumes where its
+/// predecessor failed.
+#[derive(Clone)]
+pub(crate) struct Link {
+    state: Arc<Mutex<State>>,
+}
+
+impl Link {
+    /// Creates a detached link along with the connection it presents to the
+    /// layers above.
+    pub(crate) fn new(capacity: usize) -> (Link, Connection) {
+        let (sender, receiver) = unbounded();
+        let link = Link {
+            state: Arc::new(Mutex::new(State {
+                sent: 0,
+                unacked: VecDeque::new(),
+                capacity: capacity.max(1),
+                received: 0,
+                inbound: Some(sender),
+                outbound: None,
+                attachment: 0,
+                writer: None,
+                detached: vec![],
+            })),
+        };
+        let connection = SinkStream::new(link.clone(), receiver);
+        (link, connection)
+    }
+
+    /// Returns the number of the current attachment if the link has no transport.
+    fn detachment(&self) -> Option<u64> {
+        let state = self.state.lock().unwrap();
+        if state.outbound.is_none() {
+            Some(state.attachment)
+        } else {
+            None
+        }
+    }
+
+    /// The sequence number of the last frame delivered to the layers above.
+    pub(crate) fn received(&self) -> u64 {
+        self.state.lock().unwrap().received
+    }
+
+    /// Carries the session over `transport`, replacing any previous transport.
+    /// Frames the peer has not received, as given by `received`, are
+    /// retransmitted.
+    pub(crate) fn attach(&self, transport: Connection, received: u64) {
+        let (sink, mut stream) = transport.split();
+        let (outbound, frames) = unbounded();
+        spawn(frames.map(Ok).forward(sink).map(|_| ()));
+        let attachment = {
+            let mut state = self.state.lock().unwrap();
+            state.attachment += 1;
+            state.acknowledge(received);
+            for (sequence, payload) in &state.unacked {
+                let _ = outbound.unbounded_send(Envelope::data(*sequence, payload));
+            }
+            state.outbound = Some(outbound.clone());
+            state.attachment
+        };
+        let state = self.state.clone();
+        spawn(async move {
+            while let Some(frame) = stream.next().await {
+                let mut state = state.lock().unwrap();
+                match Envelope::parse(frame) {
+                    Some(Envelope::Data(sequence, payload)) => {
+                        if sequence == state.received + 1 {
+                            let message = match Message::from_tagged(payload) {
+                                Some(message) => message,
+                                None => break,
+                            };
+                            state.received = sequence;
+                            if let Some(inbound) = &state.inbound {
+                                let _ = inbound.unbounded_send(message);
+                            }
+                        }
+                        let _ = outbound.unbounded_send(Envelope::ack(state.received));
+                    }
+                    Some(Envelope::Ack(sequence)) => state.acknowledge(sequence),
+                    None => break,
+                }
+            }
+            let mut state = state.lock().unwrap();
+            if state.attachment == attachment {
+                state.detach();
+            }
+        });
+    }
+
+    /// Resolves when the current transport is lost, or immediately if there is
+    /// none.
+    pub(crate) fn disconnected(&self) -> Future<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.outbound.is_none() {
+            return Box::pin(async {});
+        }
+        let (sender, receiver) = oneshot::channel();
+        state.detached.push(sender);
+        Box::pin(receiver.map(|_| ()))
+    }
+
+    /// Ends the session, closing the connection presented to the layers above.
+    pub(crate) fn expire(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.inbound = None;
+        state.detach();
+    }
+}
+
+impl Sink<Message> for Link {
+    type Error = ConnectionError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        let mut state = self.state.lock().unwrap();
+        if state.unacked.len() < state.capacity {
+            Poll::Ready(Ok(()))
+        } else {
+            state.writer = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+    fn start_send(self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        let item = item.into_tagged();
+        let mut state = self.state.lock().unwrap();
+        state.sent += 1;
+        let sequence = state.sent;
+        if let Some(outbound) = &state.outbound {
+            let _ = outbound.unbounded_send(Envelope::data(sequence, &item));
+        }
+        state.unacked.push_back((sequence, item));
+        Ok(())
+    }
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// The sessions held by a server, each retained for the grace period after its
+/// transport is lost.
+#[derive(Clone)]
+pub(crate) struct Sessions {
+    resumption: Resumption,
+    sessions: Arc<Mutex<HashMap<Vec<u8>, Link>>>,
+}
+
+impl Sessions {
+    pub(crate) fn new(resumption: Resumption) -> Self {
+        Sessions {
+            resumption,
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Creates a detached session, returning its identifier and the connection it
+    /// presents, or `None` if no identifier could be generated.
+    pub(crate) fn create(&self) -> Future<Option<(Vec<u8>, Connection)>> {
+        let this = self.clone();
+        Box::pin(async move {
+            let id = <dyn Rng>::new().ok()?.bytes(16).await.ok()?;
+            let (link, connection) = Link::new(this.resumption.capacity);
+            this.sessions
+                .lock()
+                .unwrap()
+                .insert(id.clone(), link.clone());
+            this.watch(id.clone(), link);
+            Some((id, connection))
+        })
+    }
+
+    /// Returns the sequence number of the last frame received by the session
+    /// identified by `id`, or `None` if the session is unknown or has expired.
+    pub(crate) fn received(&self, id: &[u8]) -> Option<u64> {
+        Some(self.sessions.lock().unwrap().get(id)?.received())
+    }
+
+    /// Carries the session identified by `id` over `transport`, retransmitting
+    /// every frame after the last one the peer has `received`.
+    pub(crate) fn attach(&self, id: &[u8], transport: Connection, received: u64) {
+        let link = self.sessions.lock().unwrap().get(id).cloned();
+        if let Some(link) = link {
+            link.attach(transport, received);
+            self.watch(id.to_vec(), link);
+        }
+    }
+
+    /// Expires the session identified by `id` once it has been detached for the
+    /// grace period without being attached again. Sessions expire as soon as they
+    /// are detached if no `Clock` is available.
+    fn watch(&self, id: Vec<u8>, link: Link) {
+        let sessions = self.sessions.clone();
+        let grace = self.resumption.grace;
+        spawn(async move {
+            link.disconnected().await;
+            let detachment = link.detachment();
+            if detachment.is_none() {
+                return;
+            }
+            let sleep = <dyn Clock>::new().map(|clock| clock.sleep(grace));
+            if let Ok(sleep) = sleep {
+                let _ = sleep.await;
+            }
+            if link.detachment() == detachment {
+                sessions.lock().unwrap().remove(&id);
+                link.expire();
+            }
+        });
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    use futures::{executor::block_on, SinkExt};
+
+    /// Creates a pair of connected transports along with a function that cuts
+    /// them, as the loss of a network connection would.
+    fn pipe() -> (Connection, Connection, impl Fn()) {
+        let (near, from_near) = unbounded();
+        let (far, from_far) = unbounded();
+        let cut = {
+            let (near, far) = (near.clone(), far.clone());
+            move || {
+                near.close_channel();
+                far.close_channel();
+            }
+        };
+        (
+            SinkStream::new(
+                near.sink_map_err(|e| ConnectionError { cause: e.into() }),
+                from_far,
+            ),
+            SinkStream::new(
+                far.sink_map_err(|e| ConnectionError { cause: e.into() }),
+                from_near,
+            ),
+            cut,
+        )
+    }
+
+    fn binary(message: Option<Message>) -> Vec<u8> {
+        match message {
+            Some(Message::Binary(data)) => data,
+            _ => panic!("expected a binary message"),
+        }
+    }
+
+    #[test]
+    fn frames_sent_while_detached_are_retransmitted() {
+        block_on(async {
+            let (client, mut client_connection) = Link::new(8);
+            let (server, mut server_connection) = Link::new(8);
+            let (near, far, cut) = pipe();
+            client.attach(near, 0);
+            server.attach(far, 0);
+            client_connection
+                .send(Message::Binary(vec![1]))
+                .await
+                .unwrap();
+            assert_eq!(binary(server_connection.next().await), vec![1]);
+            cut();
+            client.disconnected().await;
+            server.disconnected().await;
+            client_connection
+                .send(Message::Binary(vec![2]))
+                .await
+                .unwrap();
+            let (near, far, _) = pipe();
+            client.attach(near, server.received());
+            server.attach(far, client.received());
+            assert_eq!(binary(server_connection.next().await), vec![2]);
+            server_connection
+                .send(Message::Binary(vec![3]))
+                .await
+                .unwrap();
+            assert_eq!(binary(client_connection.next().await), vec![3]);
+        });
+    }
+
+    #[cfg(feature = "core")]
+    const GRACE: Duration = Duration::from_millis(50);
+
+    #[cfg(feature = "core")]
+    async fn sleep(duration: Duration) {
+        let _ = <dyn Clock>::new().unwrap().sleep(duration).await;
+    }
+
+    #[cfg(feature = "core")]
+    fn sessions() -> Sessions {
+        Sessions::new(Resumption {
+            grace: GRACE,
+            capacity: 8,
+        })
+    }
+
+    #[cfg(feature = "core")]
+    #[test]
+    fn unattached_sessions_expire() {
+        block_on(async {
+            let sessions = sessions();
+            let (id, mut connection) = sessions.create().await.unwrap();
+            assert_eq!(sessions.received(&id), Some(0));
+            sleep(GRACE * 4).await;
+            assert_eq!(sessions.received(&id), None);
+            assert!(connection.next().await.is_none());
+        });
+    }
+
+    #[cfg(feature = "core")]
+    #[test]
+    fn detached_sessions_expire_after_grace() {
+        block_on(async {
+            let sessions = sessions();
+            let (id, mut connection) = sessions.create().await.unwrap();
+            let (near, _far, cut) = pipe();
+            sessions.attach(&id, near, 0);
+            sleep(GRACE * 4).await;
+            assert_eq!(sessions.received(&id), Some(0));
+            cut();
+            sleep(GRACE * 4).await;
+            assert_eq!(sessions.received(&id), None);
+            assert!(connection.next().await.is_none());
+        });
+    }
+
+    #[cfg(feature = "core")]
+    #[test]
+    fn reattached_sessions_are_retained() {
+        block_on(async {
+            let sessions = sessions();
+            let (id, _connection) = sessions.create().await.unwrap();
+            let (near, _far, cut) = pipe();
+            sessions.attach(&id, near, 0);
+            cut();
+            sleep(GRACE / 2).await;
+            let (near, _far, _) = pipe();
+            sessions.attach(&id, near, 0);
+            sleep(GRACE * 4).await;
+            assert_eq!(sessions.received(&id), Some(0));
+        });
+    }
+}
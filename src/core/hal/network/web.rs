@@ -1,4 +1,4 @@
-use super::{ConnectError, ConnectionError, RawClient};
+use super::{ConnectError, ConnectionError, Message, RawClient};
 
 use crate::{core::spawn, kind::Fallible, kind::SinkStream, SyncSendAssert};
 
@@ -25,7 +25,7 @@ impl RawClient for Client {
     fn connect(
         &mut self,
         address: Url,
-    ) -> Fallible<SinkStream<Vec<u8>, ConnectionError, Vec<u8>>, ConnectError> {
+    ) -> Fallible<SinkStream<Message, ConnectionError, Message>, ConnectError> {
         Box::pin(SyncSendAssert(Box::pin(async move {
             let socket = WebSocket::new(&address.into_string())
                 .map_err(|_| ConnectError::Connect(SecurityError.into()))?;
@@ -37,16 +37,25 @@ impl RawClient for Client {
             socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
             let (mut data_sender, data_receiver) = unbounded();
             let on_data = Closure::wrap(Box::new(move |e: MessageEvent| {
+                let data = e.data();
                 data_sender
-                    .unbounded_send(Uint8Array::new(&e.data()).to_vec())
+                    .unbounded_send(match data.as_string() {
+                        Some(data) => Message::Text(data),
+                        None => Message::Binary(Uint8Array::new(&data).to_vec()),
+                    })
                     .unwrap();
             }) as Box<dyn FnMut(_)>);
             socket.set_onmessage(Some(on_data.as_ref().unchecked_ref()));
             on_data.forget();
-            let (out_sender, mut out_receiver): (_, UnboundedReceiver<Vec<u8>>) = unbounded();
+            let (out_sender, mut out_receiver): (_, UnboundedReceiver<Message>) = unbounded();
             spawn(SyncSendAssert(Box::pin(async move {
-                while let Some(mut data) = out_receiver.next().await {
-                    socket.send_with_u8_array(data.as_mut_slice()).unwrap();
+                while let Some(item) = out_receiver.next().await {
+                    match item {
+                        Message::Binary(mut data) => {
+                            socket.send_with_u8_array(data.as_mut_slice()).unwrap()
+                        }
+                        Message::Text(data) => socket.send_with_str(&data).unwrap(),
+                    }
                 }
             })));
             receiver.await.unwrap();
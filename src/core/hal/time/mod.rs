@@ -0,0 +1,39 @@
+use crate::{
+    core::UnimplementedError,
+    kind::{Infallible, Stream},
+    object,
+};
+
+use std::time::{Duration, SystemTime};
+
+#[object]
+pub trait Clock {
+    /// Resolves once `duration` has elapsed.
+    fn sleep(&self, duration: Duration) -> Infallible<()>;
+    /// Yields once every `period`, beginning one `period` from now, until the
+    /// stream is dropped. A zero `period` yields nothing and ends immediately.
+    fn interval(&self, period: Duration) -> Stream<()>;
+    /// The current wall-clock time.
+    fn now(&self) -> Infallible<SystemTime>;
+    /// The time elapsed since a fixed point determined by the implementation,
+    /// which never decreases.
+    fn monotonic_now(&self) -> Infallible<Duration>;
+}
+
+#[cfg(all(not(target_arch = "wasm32"), feature = "core"))]
+mod native;
+#[cfg(all(target_arch = "wasm32", feature = "core"))]
+mod web;
+
+impl dyn Clock {
+    pub fn new() -> Result<Box<dyn Clock>, UnimplementedError> {
+        #[cfg(all(target_arch = "wasm32", feature = "core"))]
+        return Ok(web::Clock::new());
+        #[cfg(all(not(target_arch = "wasm32"), feature = "core"))]
+        return Ok(native::Clock::new());
+        #[cfg(not(feature = "core"))]
+        return Err(UnimplementedError {
+            feature: "timers and clocks".to_owned(),
+        });
+    }
+}
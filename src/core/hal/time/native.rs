@@ -0,0 +1,230 @@
+use super::Clock as IClock;
+
+use crate::kind::{Infallible, Stream};
+
+use core::cmp::Ordering;
+use futures::{
+    channel::{
+        mpsc::{unbounded, UnboundedSender},
+        oneshot,
+    },
+    future::pending,
+    stream::{empty, once},
+    FutureExt,
+};
+use lazy_static::lazy_static;
+use std::{
+    collections::BinaryHeap,
+    sync::{
+        mpsc::{channel, Receiver, RecvTimeoutError, Sender},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+lazy_static! {
+    static ref ORIGIN: Instant = Instant::now();
+    static ref TIMERS: Mutex<Sender<Timer>> = {
+        let (sender, receiver) = channel();
+        thread::spawn(move || run(receiver));
+        Mutex::new(sender)
+    };
+}
+
+enum Wake {
+    Once(oneshot::Sender<()>),
+    Every(UnboundedSender<()>, Duration),
+}
+
+struct Timer {
+    deadline: Instant,
+    wake: Wake,
+}
+
+impl PartialEq for Timer {
+    fn eq(&self, other: &Self) -> bool {
+        self.deadline == other.deadline
+    }
+}
+
+impl Eq for Timer {}
+
+impl PartialOrd for Timer {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Timer {
+    // reversed so that the heap yields the earliest deadline first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.deadline.cmp(&self.deadline)
+    }
+}
+
+fn schedule(timer: Timer) {
+    let _ = TIMERS.lock().unwrap().send(timer);
+}
+
+// All timers of the process are served by this one thread, which sleeps until
+// the earliest deadline or the arrival of a new timer. A timer whose receiver
+// has been dropped is discarded when it next comes due. An interval whose next
+// deadline cannot be represented never fires again, so it is kept without a
+// deadline until its stream is dropped.
+fn run(receiver: Receiver<Timer>) {
+    let mut timers = BinaryHeap::new();
+    let mut never: Vec<UnboundedSender<()>> = vec![];
+    loop {
+        let timer = match timers.peek() {
+            Some(Timer { deadline, .. }) => {
+                match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                    Ok(timer) => Some(timer),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+            None => match receiver.recv() {
+                Ok(timer) => Some(timer),
+                Err(_) => return,
+            },
+        };
+        if let Some(timer) = timer {
+            timers.push(timer);
+        }
+        let now = Instant::now();
+        while timers.peek().map_or(false, |timer| timer.deadline <= now) {
+            let Timer { deadline, wake } = timers.pop().unwrap();
+            match wake {
+                Wake::Once(sender) => {
+                    let _ = sender.send(());
+                }
+                Wake::Every(sender, period) => {
+                    if sender.unbounded_send(()).is_ok() {
+                        match deadline.checked_add(period) {
+                            Some(deadline) => timers.push(Timer {
+                                deadline,
+                                wake: Wake::Every(sender, period),
+                            }),
+                            None => never.push(sender),
+                        }
+                    }
+                }
+            }
+        }
+        never.retain(|sender| !sender.is_closed());
+    }
+}
+
+pub struct Clock;
+
+impl IClock for Clock {
+    fn sleep(&self, duration: Duration) -> Infallible<()> {
+        // a deadline that cannot be represented is never reached
+        let deadline = match Instant::now().checked_add(duration) {
+            Some(deadline) => deadline,
+            None => return Box::pin(pending()),
+        };
+        let (sender, receiver) = oneshot::channel();
+        schedule(Timer {
+            deadline,
+            wake: Wake::Once(sender),
+        });
+        Box::pin(receiver.map(|_| Ok(())))
+    }
+    fn interval(&self, period: Duration) -> Stream<()> {
+        if period == Duration::from_secs(0) {
+            return Box::pin(empty());
+        }
+        let deadline = match Instant::now().checked_add(period) {
+            Some(deadline) => deadline,
+            None => return Box::pin(once(pending())),
+        };
+        let (sender, receiver) = unbounded();
+        schedule(Timer {
+            deadline,
+            wake: Wake::Every(sender, period),
+        });
+        Box::pin(receiver)
+    }
+    fn now(&self) -> Infallible<SystemTime> {
+        let now = SystemTime::now();
+        Box::pin(async move { Ok(now) })
+    }
+    fn monotonic_now(&self) -> Infallible<Duration> {
+        let now = ORIGIN.elapsed();
+        Box::pin(async move { Ok(now) })
+    }
+}
+
+impl Clock {
+    pub fn new() -> Box<dyn IClock> {
+        Box::new(Clock)
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::Clock;
+    use crate::core::hal::time::Clock as _;
+
+    use futures::{
+        executor::block_on,
+        future::{select, Either},
+        StreamExt,
+    };
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn sleeps_resolve_by_deadline() {
+        let clock = Clock;
+        let start = Instant::now();
+        let long = clock.sleep(Duration::from_millis(200));
+        let short = clock.sleep(Duration::from_millis(20));
+        block_on(async move {
+            match select(long, short).await {
+                Either::Right((result, _)) => result.unwrap(),
+                _ => panic!("the longer sleep resolved first"),
+            }
+        });
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(20) && elapsed < Duration::from_millis(200));
+    }
+
+    #[test]
+    fn intervals_tick_until_dropped() {
+        let clock = Clock;
+        let ticks = block_on(
+            clock
+                .interval(Duration::from_millis(5))
+                .take(3)
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(ticks.len(), 3);
+    }
+
+    #[test]
+    fn unrepresentable_deadlines_never_fire() {
+        let clock = Clock;
+        let sleep = clock.sleep(Duration::new(u64::max_value(), 0));
+        let tick = clock
+            .interval(Duration::new(u64::max_value(), 0))
+            .into_future();
+        let short = clock.sleep(Duration::from_millis(20));
+        block_on(async move {
+            match select(select(sleep, tick), short).await {
+                Either::Right((result, _)) => result.unwrap(),
+                _ => panic!("an unrepresentable deadline was reached"),
+            }
+        });
+        // the timer thread still serves later timers
+        block_on(clock.sleep(Duration::from_millis(1))).unwrap();
+    }
+
+    #[test]
+    fn zero_periods_yield_nothing() {
+        let clock = Clock;
+        let ticks = block_on(clock.interval(Duration::from_secs(0)).collect::<Vec<_>>());
+        assert!(ticks.is_empty());
+    }
+}
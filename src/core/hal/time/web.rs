@@ -0,0 +1,83 @@
+use super::Clock as IClock;
+
+use crate::kind::{Infallible, Stream};
+
+use core::cell::Cell;
+use futures::{
+    channel::{mpsc::unbounded, oneshot},
+    stream::empty,
+    FutureExt,
+};
+use std::{
+    rc::Rc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use wasm_bindgen::{closure::Closure, JsCast};
+
+// Browsers take timeouts as a signed 32-bit count of milliseconds, so longer
+// durations are clamped to roughly 24.8 days rather than wrapping around.
+fn millis(duration: Duration) -> i32 {
+    duration.as_millis().min(i32::max_value() as u128) as i32
+}
+
+pub struct Clock;
+
+impl IClock for Clock {
+    fn sleep(&self, duration: Duration) -> Infallible<()> {
+        let (sender, receiver) = oneshot::channel();
+        let callback = Closure::once_into_js(move || {
+            let _ = sender.send(());
+        });
+        web_sys::window()
+            .unwrap()
+            .set_timeout_with_callback_and_timeout_and_arguments_0(
+                callback.unchecked_ref(),
+                millis(duration),
+            )
+            .unwrap();
+        Box::pin(receiver.map(|_| Ok(())))
+    }
+    fn interval(&self, period: Duration) -> Stream<()> {
+        if period == Duration::from_secs(0) {
+            return Box::pin(empty());
+        }
+        let (sender, receiver) = unbounded();
+        let id = Rc::new(Cell::new(0));
+        let handle = id.clone();
+        let callback = Closure::wrap(Box::new(move || {
+            if sender.unbounded_send(()).is_err() {
+                web_sys::window()
+                    .unwrap()
+                    .clear_interval_with_handle(handle.get());
+            }
+        }) as Box<dyn FnMut()>);
+        id.set(
+            web_sys::window()
+                .unwrap()
+                .set_interval_with_callback_and_timeout_and_arguments_0(
+                    callback.as_ref().unchecked_ref(),
+                    millis(period),
+                )
+                .unwrap(),
+        );
+        // the callback lives as long as the interval, which it clears once the
+        // stream is dropped
+        callback.forget();
+        Box::pin(receiver)
+    }
+    fn now(&self) -> Infallible<SystemTime> {
+        let now = UNIX_EPOCH + Duration::from_micros((js_sys::Date::now() * 1000.) as u64);
+        Box::pin(async move { Ok(now) })
+    }
+    fn monotonic_now(&self) -> Infallible<Duration> {
+        let now = web_sys::window().unwrap().performance().unwrap().now();
+        let now = Duration::from_micros((now * 1000.) as u64);
+        Box::pin(async move { Ok(now) })
+    }
+}
+
+impl Clock {
+    pub fn new() -> Box<dyn IClock> {
+        Box::new(Clock)
+    }
+}
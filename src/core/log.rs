@@ -0,0 +1,160 @@
+//! Levelled, structured logging of vessels.
+//!
+//! Within a vessel, records emitted with [`log!`](../../macro.log.html) are sent
+//! to the [`Log`](trait.Log.html) capability of its handle. The host provides
+//! that capability with a [`Router`](struct.Router.html), which attaches the id
+//! of the emitting vessel and routes each record to its destination. An
+//! orchestrator assigns that id when instantiating a vessel with a router.
+//! ```ignore
+//! let router = Router::stdout().with_level(Level::Debug);
+//! let value: String = orchestrator.instantiate_logged(module, scope, router).await?;
+//! ```
+
+use crate::{kind::Infallible, object, Kind};
+
+use core::fmt::{self, Display, Formatter};
+use futures::channel::mpsc::unbounded;
+use std::sync::Arc;
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    fs::OpenOptions,
+    io::{self, Write},
+    path::Path,
+    sync::Mutex,
+};
+
+#[derive(Kind, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        use Level::{Debug, Error, Info, Trace, Warn};
+
+        write!(
+            f,
+            "{}",
+            match self {
+                Trace => "TRACE",
+                Debug => "DEBUG",
+                Info => "INFO",
+                Warn => "WARN",
+                Error => "ERROR",
+            }
+        )
+    }
+}
+
+/// The logging capability provided to vessels.
+#[object]
+pub trait Log {
+    fn record(
+        &self,
+        level: Level,
+        message: String,
+        fields: Vec<(String, String)>,
+    ) -> Infallible<()>;
+}
+
+/// A record emitted by a vessel, as received by the host.
+#[derive(Clone, Debug)]
+pub struct Record {
+    /// The id the host assigned to the emitting vessel.
+    pub instance: u64,
+    pub level: Level,
+    pub message: String,
+    pub fields: Vec<(String, String)>,
+}
+
+impl Display for Record {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "[{}] {} {}", self.instance, self.level, self.message)?;
+        for (key, value) in &self.fields {
+            write!(f, " {}={}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Routes the records of vessels to a destination, discarding those below its
+/// level.
+#[derive(Clone)]
+pub struct Router {
+    route: Arc<dyn Fn(Record) + Sync + Send>,
+    level: Level,
+}
+
+impl Router {
+    /// Creates a router that passes each record to `route`.
+    pub fn new(route: impl Fn(Record) + Sync + Send + 'static) -> Self {
+        Router {
+            route: Arc::new(route),
+            level: Level::Info,
+        }
+    }
+    /// Creates a router that writes records to standard output.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn stdout() -> Self {
+        Router::new(|record| println!("{}", record))
+    }
+    /// Creates a router that appends records to the file at `path`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = Mutex::new(OpenOptions::new().create(true).append(true).open(path)?);
+        Ok(Router::new(move |record| {
+            let _ = writeln!(file.lock().unwrap(), "{}", record);
+        }))
+    }
+    /// Creates a router that forwards records to the returned subscriber.
+    pub fn subscriber() -> (Self, crate::kind::Stream<Record>) {
+        let (sender, receiver) = unbounded();
+        (
+            Router::new(move |record| {
+                let _ = sender.unbounded_send(record);
+            }),
+            Box::pin(receiver),
+        )
+    }
+    /// Sets the least severe level of the records routed, which is `Info` by
+    /// default.
+    pub fn with_level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+    /// Creates the `Log` capability for the vessel identified by `instance`.
+    pub fn instance(&self, instance: u64) -> Box<dyn Log> {
+        Box::new(Instance {
+            router: self.clone(),
+            instance,
+        })
+    }
+}
+
+struct Instance {
+    router: Router,
+    instance: u64,
+}
+
+impl Log for Instance {
+    fn record(
+        &self,
+        level: Level,
+        message: String,
+        fields: Vec<(String, String)>,
+    ) -> Infallible<()> {
+        if level >= self.router.level {
+            (self.router.route)(Record {
+                instance: self.instance,
+                level,
+                message,
+                fields,
+            });
+        }
+        Box::pin(async { Ok(()) })
+    }
+}
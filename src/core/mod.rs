@@ -1,26 +1,49 @@
 use alloc::sync::Arc;
 use anyhow::Error;
+use core::any::type_name;
 #[cfg(any(target_arch = "wasm32", feature = "core"))]
 use core::any::Any;
-use futures::{lock, SinkExt, StreamExt};
+#[cfg(any(target_arch = "wasm32", feature = "core"))]
+use futures::channel::mpsc::UnboundedReceiver;
+#[cfg(all(target_arch = "wasm32", not(feature = "core")))]
+use futures::FutureExt;
+use futures::{
+    channel::mpsc::{unbounded, UnboundedSender},
+    future::{ready, select, Either},
+    SinkExt, StreamExt,
+};
 use lazy_static::lazy_static;
-use std::{collections::HashMap, sync::Mutex};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
 use thiserror::Error;
 
 use crate::{
-    channel::IdChannel,
-    format::{ApplyDecode, ApplyEncode, Cbor},
-    kind::{Fallible, Infallible, SinkStream, TransportError},
+    channel::{Context as _, IdChannel, Target, Waiter},
+    format::{decode_with, ApplyEncode, Cbor},
+    kind::{Fallible, Infallible, SinkStream, Stream, TransportError},
     object,
+    reflect::{Reflected, Trait},
     replicate::Share,
     Kind, OnTo,
 };
 
 mod executor;
 pub use executor::{run, spawn};
+mod revocable;
+use revocable::{Grant, NOTICE};
+mod scope;
+pub use revocable::{Revoker, Terms};
+use scope::Delegate;
+pub use scope::Scope;
 
 pub mod data;
 pub mod hal;
+pub mod log;
 pub mod orchestrator;
 
 #[doc(hidden)]
@@ -41,26 +64,120 @@ pub enum CoreError {
     #[error("`handle transfer failed: {0}")]
     Construct(#[source] Error),
     #[error("`underlying transport failed: {0}")]
-    Transport(#[from] TransportError),
+    Transport(#[source] TransportError),
+    #[error("capability revoked or expired")]
+    Revoked,
+    #[error("timed out waiting for capability")]
+    Timeout,
+}
+
+impl From<TransportError> for CoreError {
+    fn from(error: TransportError) -> Self {
+        // the transport to a capability ends when the capability is revoked
+        match error.cause::<CoreError>() {
+            Some(CoreError::Revoked) => CoreError::Revoked,
+            _ => CoreError::Transport(error),
+        }
+    }
+}
+
+/// A change to the capabilities offered by a `Handle`, identified by the hash of
+/// their Kind.
+#[derive(Kind, Debug, Clone)]
+pub enum Event {
+    Registered([u8; 32]),
+    Unregistered([u8; 32]),
+}
+
+impl Event {
+    pub fn kind(&self) -> [u8; 32] {
+        match self {
+            Event::Registered(kind) | Event::Unregistered(kind) => *kind,
+        }
+    }
 }
 
+/// A description of a capability offered by a `Handle`.
+#[derive(Kind, Debug, Clone)]
+pub struct Descriptor {
+    /// The hash of the capability's Kind, by which it is acquired.
+    pub kind: [u8; 32],
+    /// The name of the capability's trait if it was registered as an object,
+    /// otherwise the name of its type as given by `core::any::type_name`. The
+    /// latter is a best-effort description for debugging that may differ between
+    /// compiler versions, so capabilities should be identified by `kind`.
+    pub name: String,
+    /// The names of the methods of the capability's trait if it was registered
+    /// as an object.
+    pub methods: Vec<String>,
+}
+
+#[cfg(all(target_arch = "wasm32", not(feature = "core")))]
+type Entry = (log::Level, String, Vec<(String, String)>);
+
 #[doc(hidden)]
-pub struct Logger(());
+pub struct Logger {
+    #[cfg(all(target_arch = "wasm32", not(feature = "core")))]
+    log: UnboundedSender<Entry>,
+}
 
 impl Logger {
-    pub fn info(&self, _message: String) {
-        #[cfg(all(target_arch = "wasm32", feature = "core"))]
-        web_sys::console::log_1(&_message.into());
+    pub fn info(&self, message: String) {
+        self.log(log::Level::Info, message, vec![]);
+    }
+    pub fn log(&self, level: log::Level, message: String, fields: Vec<(String, String)>) {
         #[cfg(all(target_arch = "wasm32", not(feature = "core")))]
-        unimplemented!();
-        #[cfg(not(target_arch = "wasm32"))]
-        println!("{}", _message);
+        {
+            let _ = self.log.unbounded_send((level, message, fields));
+        }
+        #[cfg(any(not(target_arch = "wasm32"), feature = "core"))]
+        {
+            let mut line = format!("{} {}", level, message);
+            for (key, value) in fields {
+                line.push_str(&format!(" {}={}", key, value));
+            }
+            #[cfg(all(target_arch = "wasm32", feature = "core"))]
+            {
+                use web_sys::console;
+                let line = wasm_bindgen::JsValue::from(line);
+                match level {
+                    log::Level::Trace | log::Level::Debug => console::debug_1(&line),
+                    log::Level::Info => console::log_1(&line),
+                    log::Level::Warn => console::warn_1(&line),
+                    log::Level::Error => console::error_1(&line),
+                }
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            println!("{}", line);
+        }
     }
 }
 
 lazy_static! {
     #[doc(hidden)]
-    pub static ref LOG: Logger = Logger(());
+    pub static ref LOG: Logger = Logger {
+        #[cfg(all(target_arch = "wasm32", not(feature = "core")))]
+        log: drain(),
+    };
+}
+
+/// Spawns the task that passes records to the `Log` capability in the order they
+/// were emitted, acquiring it on the first record.
+#[cfg(all(target_arch = "wasm32", not(feature = "core")))]
+fn drain() -> UnboundedSender<Entry> {
+    let (sender, mut receiver) = unbounded::<Entry>();
+    spawn(async move {
+        let mut capability = None;
+        while let Some((level, message, fields)) = receiver.next().await {
+            if capability.is_none() {
+                capability = acquire::<Box<dyn log::Log>>().await.ok();
+            }
+            if let Some(capability) = &capability {
+                let _ = capability.record(level, message, fields).await;
+            }
+        }
+    });
+    sender
 }
 
 #[cfg(all(target_arch = "wasm32", not(feature = "core")))]
@@ -109,9 +226,158 @@ pub fn acquire<K: Kind>() -> Fallible<K, CoreError> {
     Box::pin(async { Err(CoreError::Unavailable) })
 }
 
+/// Creates a `Scope` of the handle provided to this vessel, allowing it to
+/// delegate a subset of its capabilities to the vessels it spawns.
+pub fn scope() -> Result<Scope, CoreError> {
+    #[cfg(all(target_arch = "wasm32", not(feature = "core")))]
+    return HANDLE
+        .lock()
+        .unwrap()
+        .0
+        .as_mut()
+        .map(Handle::scope)
+        .ok_or(CoreError::Unavailable);
+    #[cfg(not(all(target_arch = "wasm32", not(feature = "core"))))]
+    Err(CoreError::Unavailable)
+}
+
+/// Acquires a capability, waiting until it is registered if it is not yet
+/// available. Capabilities registered in this process with
+/// [`register`](fn.register.html) are waited for, as well as, within a vessel,
+/// those of the handle provided to it. Fails with `CoreError::Timeout` if
+/// `deadline` resolves first. As
+/// [`Clock::sleep`](hal/time/trait.Clock.html#tymethod.sleep) yields a `Result`,
+/// it is mapped to `()` to serve as a deadline.
+/// ```ignore
+/// let deadline = <dyn Clock>::new()?.sleep(Duration::from_secs(5)).map(|_| ());
+/// let hasher: Box<dyn Hasher> = acquire_when_available(deadline).await?;
+/// ```
+pub fn acquire_when_available<K: Kind>(
+    deadline: impl core::future::Future<Output = ()> + Sync + Send + 'static,
+) -> Fallible<K, CoreError> {
+    #[cfg(feature = "core")]
+    return {
+        let registry = LOCAL_CORE.lock().unwrap();
+        if let Some(item) = registry.get(&K::USE_KIND_MACRO_TO_GENERATE_THIS_FIELD) {
+            let item = Ok(*Box::<dyn Any>::downcast((item)()).unwrap());
+            return Box::pin(async move { item });
+        }
+        registered(subscribe(), deadline)
+    };
+    #[cfg(all(target_arch = "wasm32", not(feature = "core")))]
+    return {
+        let handle = HANDLE.lock().unwrap();
+        if let Some(item) = handle.1.get(&K::USE_KIND_MACRO_TO_GENERATE_THIS_FIELD) {
+            let item = Ok(*Box::<dyn Any>::downcast((item)()).unwrap());
+            return Box::pin(async move { item });
+        }
+        let registrations = subscribe();
+        if let Some(handle) = &handle.0 {
+            let deadline = deadline.shared();
+            let remote = handle.acquire_when_available::<K>(deadline.clone());
+            let local = registered(registrations, deadline);
+            Box::pin(async move {
+                match select(local, remote).await {
+                    Either::Left((item, _)) | Either::Right((item, _)) => item,
+                }
+            })
+        } else {
+            registered(registrations, deadline)
+        }
+    };
+    #[cfg(not(any(feature = "core", target_arch = "wasm32")))]
+    {
+        drop(deadline);
+        Box::pin(async { Err(CoreError::Unavailable) })
+    }
+}
+
+#[cfg(any(feature = "core", target_arch = "wasm32"))]
+lazy_static! {
+    static ref REGISTRATIONS: Mutex<Vec<UnboundedSender<[u8; 32]>>> = Mutex::new(vec![]);
+}
+
+// must be called while the registry is locked, so that no registration can occur
+// between the lookup that missed and the subscription
+#[cfg(any(feature = "core", target_arch = "wasm32"))]
+fn subscribe() -> UnboundedReceiver<[u8; 32]> {
+    let (sender, receiver) = unbounded();
+    REGISTRATIONS.lock().unwrap().push(sender);
+    receiver
+}
+
+/// Resolves with the capability `K` once it is registered in this process, or
+/// fails with `CoreError::Timeout` if `deadline` resolves first.
+#[cfg(any(feature = "core", target_arch = "wasm32"))]
+fn registered<K: Kind>(
+    mut registrations: UnboundedReceiver<[u8; 32]>,
+    deadline: impl core::future::Future<Output = ()> + Sync + Send + 'static,
+) -> Fallible<K, CoreError> {
+    let registration = async move {
+        while let Some(ty) = registrations.next().await {
+            if ty == K::USE_KIND_MACRO_TO_GENERATE_THIS_FIELD {
+                return;
+            }
+        }
+    };
+    Box::pin(async move {
+        match select(Box::pin(registration), Box::pin(deadline)).await {
+            Either::Left(_) => {
+                #[cfg(feature = "core")]
+                let registry = LOCAL_CORE.lock().unwrap();
+                #[cfg(not(feature = "core"))]
+                let handle = HANDLE.lock().unwrap();
+                #[cfg(not(feature = "core"))]
+                let registry = &handle.1;
+                registry
+                    .get(&K::USE_KIND_MACRO_TO_GENERATE_THIS_FIELD)
+                    .map(|item| *Box::<dyn Any>::downcast((item)()).unwrap())
+                    .ok_or(CoreError::Unavailable)
+            }
+            Either::Right(_) => Err(CoreError::Timeout),
+        }
+    })
+}
+
+/// Notifies this vessel of changes to the capabilities of the handle provided
+/// to it.
+pub fn events() -> Result<Stream<Event>, CoreError> {
+    #[cfg(all(target_arch = "wasm32", not(feature = "core")))]
+    return HANDLE
+        .lock()
+        .unwrap()
+        .0
+        .as_ref()
+        .map(Handle::events)
+        .ok_or(CoreError::Unavailable);
+    #[cfg(not(all(target_arch = "wasm32", not(feature = "core"))))]
+    Err(CoreError::Unavailable)
+}
+
+/// Lists the capabilities of the handle provided to this vessel, allowing it to
+/// detect which features are available to it.
+pub fn capabilities() -> Fallible<Vec<Descriptor>, CoreError> {
+    #[cfg(all(target_arch = "wasm32", not(feature = "core")))]
+    return {
+        if let Some(handle) = &HANDLE.lock().unwrap().0 {
+            handle.capabilities()
+        } else {
+            Box::pin(async { Err(CoreError::Unavailable) })
+        }
+    };
+    #[cfg(not(all(target_arch = "wasm32", not(feature = "core"))))]
+    Box::pin(async { Err(CoreError::Unavailable) })
+}
+
 #[object]
 trait HandleInner {
     fn acquire(&self, ty: [u8; 32]) -> Fallible<SinkStream<Vec<u8>, Error, Vec<u8>>, CoreError>;
+    fn capabilities(&self) -> Fallible<Vec<Descriptor>, CoreError>;
+    fn acquire_when_available(
+        &self,
+        ty: [u8; 32],
+    ) -> Fallible<SinkStream<Vec<u8>, Error, Vec<u8>>, CoreError>;
+    fn events(&self) -> Stream<Event>;
 }
 
 #[cfg(feature = "core")]
@@ -125,41 +391,135 @@ pub struct Handle(Box<dyn HandleInner>);
 
 impl Handle {
     pub fn acquire<K: Kind>(&self) -> Fallible<K, CoreError> {
-        let channel = self.0.acquire(K::USE_KIND_MACRO_TO_GENERATE_THIS_FIELD);
+        Handle::construct(self.0.acquire(K::USE_KIND_MACRO_TO_GENERATE_THIS_FIELD))
+    }
+    /// Acquires a capability, waiting until it is registered if it is not yet
+    /// available. Fails with `CoreError::Timeout` if `deadline`, such as a
+    /// [`Clock::sleep`](hal/time/trait.Clock.html#tymethod.sleep) mapped to
+    /// `()`, resolves first.
+    pub fn acquire_when_available<K: Kind>(
+        &self,
+        deadline: impl core::future::Future<Output = ()> + Sync + Send + 'static,
+    ) -> Fallible<K, CoreError> {
+        let channel = self
+            .0
+            .acquire_when_available(K::USE_KIND_MACRO_TO_GENERATE_THIS_FIELD);
+        Handle::construct(Box::pin(async move {
+            match select(channel, Box::pin(deadline)).await {
+                Either::Left((channel, _)) => channel,
+                Either::Right(_) => Err(CoreError::Timeout),
+            }
+        }))
+    }
+    /// Notifies the holder of capabilities registered with or unregistered from
+    /// this handle.
+    pub fn events(&self) -> Stream<Event> {
+        self.0.events()
+    }
+    fn construct<K: Kind>(
+        channel: Fallible<SinkStream<Vec<u8>, Error, Vec<u8>>, CoreError>,
+    ) -> Fallible<K, CoreError> {
         Box::pin(async move {
-            channel
-                .await?
-                .sink_map_err(CoreError::Construct)
-                .decode::<IdChannel, Cbor>()
+            let shim = <IdChannel as Target<'static, K>>::new_shim();
+            let (sink, stream) = channel.await?.sink_map_err(CoreError::Construct).split();
+            let revoked = Arc::new(AtomicBool::new(false));
+            // a revoked capability sends a notice before ending the transport, so
+            // that the calls that end as a result fail as revoked
+            let stream = stream.scan(
+                (shim.context(), revoked.clone()),
+                |(context, revoked), frame| {
+                    ready(if frame == NOTICE {
+                        revoked.store(true, Ordering::SeqCst);
+                        context.fail(CoreError::Revoked.into());
+                        None
+                    } else {
+                        Some(frame)
+                    })
+                },
+            );
+            decode_with::<Cbor, _, K, IdChannel, _>(SinkStream::new(sink, stream), shim)
                 .await
-                .map_err(|e: K::ConstructError| CoreError::Construct(e.into()))
+                .map_err(|e: K::ConstructError| {
+                    if revoked.load(Ordering::SeqCst) {
+                        CoreError::Revoked
+                    } else {
+                        CoreError::Construct(e.into())
+                    }
+                })
         })
     }
+    /// Lists the capabilities that can be acquired through this handle.
+    pub fn capabilities(&self) -> Fallible<Vec<Descriptor>, CoreError> {
+        self.0.capabilities()
+    }
+    /// Creates a `Scope` from which a child handle exposing a subset of this
+    /// handle's capabilities is derived. This handle remains usable alongside
+    /// its children.
+    pub fn scope(&mut self) -> Scope {
+        let inner = Arc::new(std::mem::replace(
+            &mut self.0,
+            Box::new(Core::new()) as Box<dyn HandleInner>,
+        ));
+        self.0 = Box::new(Delegate(inner.clone()));
+        Scope::new(Delegate(inner))
+    }
+}
+
+struct Capability {
+    acquire:
+        Box<dyn Fn() -> Fallible<SinkStream<Vec<u8>, Error, Vec<u8>>, CoreError> + Sync + Send>,
+    describe: Box<dyn Fn() -> Option<Descriptor> + Sync + Send>,
 }
 
 pub struct Core {
-    capabilities: Arc<
-        Mutex<
-            HashMap<
-                [u8; 32],
-                Box<
-                    dyn Fn() -> Fallible<SinkStream<Vec<u8>, Error, Vec<u8>>, CoreError>
-                        + Sync
-                        + Send,
-                >,
-            >,
-        >,
-    >,
+    capabilities: Arc<Mutex<HashMap<[u8; 32], Capability>>>,
+    listeners: Arc<Mutex<Vec<UnboundedSender<Event>>>>,
 }
 
 impl HandleInner for Core {
     fn acquire(&self, ty: [u8; 32]) -> Fallible<SinkStream<Vec<u8>, Error, Vec<u8>>, CoreError> {
         if let Some(capability) = self.capabilities.lock().unwrap().get(&ty) {
-            capability()
+            (capability.acquire)()
         } else {
             Box::pin(async move { Err(CoreError::Unavailable) })
         }
     }
+    fn capabilities(&self) -> Fallible<Vec<Descriptor>, CoreError> {
+        let descriptors = self
+            .capabilities
+            .lock()
+            .unwrap()
+            .values()
+            .filter_map(|capability| (capability.describe)())
+            .collect();
+        Box::pin(async move { Ok(descriptors) })
+    }
+    fn acquire_when_available(
+        &self,
+        ty: [u8; 32],
+    ) -> Fallible<SinkStream<Vec<u8>, Error, Vec<u8>>, CoreError> {
+        // subscribe before checking so that a registration in between is not missed
+        let mut events = self.events();
+        if self.capabilities.lock().unwrap().contains_key(&ty) {
+            return self.acquire(ty);
+        }
+        let core = self.share();
+        Box::pin(async move {
+            while let Some(event) = events.next().await {
+                if let Event::Registered(kind) = event {
+                    if kind == ty {
+                        return core.acquire(ty).await;
+                    }
+                }
+            }
+            Err(CoreError::Unavailable)
+        })
+    }
+    fn events(&self) -> Stream<Event> {
+        let (sender, receiver) = unbounded();
+        self.listeners.lock().unwrap().push(sender);
+        Box::pin(receiver)
+    }
 }
 
 pub fn register<K: Kind>(item: impl Fn() -> K + Sync + Send + 'static) {
@@ -177,30 +537,121 @@ pub fn register<K: Kind>(item: impl Fn() -> K + Sync + Send + 'static) {
             Box::new(move || Box::new(item())),
         );
     }
+    #[cfg(any(feature = "core", target_arch = "wasm32"))]
+    REGISTRATIONS.lock().unwrap().retain(|listener| {
+        listener
+            .unbounded_send(K::USE_KIND_MACRO_TO_GENERATE_THIS_FIELD)
+            .is_ok()
+    });
 }
 
 impl Core {
     pub fn new() -> Self {
         Core {
             capabilities: Arc::new(Mutex::new(HashMap::new())),
+            listeners: Arc::new(Mutex::new(vec![])),
         }
     }
     pub fn register<K: Kind>(&mut self, item: impl Fn() -> K + Sync + Send + 'static) {
-        let item = Arc::new(lock::Mutex::new(item));
-        self.capabilities.lock().unwrap().insert(
-            K::USE_KIND_MACRO_TO_GENERATE_THIS_FIELD,
+        self.insert(item, None, |_| (type_name::<K>().to_owned(), vec![]));
+    }
+    /// Registers a capability that is a trait object, which is described by the
+    /// name and methods of its trait. `item` is called once on registration to
+    /// obtain this description.
+    pub fn register_object<T: Reflected + Trait<T> + ?Sized>(
+        &mut self,
+        item: impl Fn() -> Box<T> + Sync + Send + 'static,
+    ) where
+        Box<T>: Kind,
+    {
+        self.insert(item, None, |item| {
+            let object = item();
+            let methods = (0..Trait::<T>::count(&*object))
+                .filter_map(|index| Trait::<T>::name_of(&*object, index).ok())
+                .collect();
+            (Trait::<T>::name(&*object), methods)
+        });
+    }
+    /// Registers a capability that remains available only under the provided
+    /// `terms` and until revoked using the returned `Revoker`. Once revoked or
+    /// expired, acquiring the capability fails and calls on objects previously
+    /// derived from it fail with `CoreError::Revoked`.
+    pub fn register_revocable<K: Kind>(
+        &mut self,
+        item: impl Fn() -> K + Sync + Send + 'static,
+        terms: Terms,
+    ) -> Revoker {
+        let grant = Grant::new(terms);
+        let revoker = Revoker::new(grant.clone());
+        self.insert(item, Some(grant), |_| (type_name::<K>().to_owned(), vec![]));
+        revoker
+    }
+    fn insert<K: Kind, F: Fn() -> K + Sync + Send + 'static>(
+        &mut self,
+        item: F,
+        grant: Option<Grant>,
+        describe: fn(&F) -> (String, Vec<String>),
+    ) {
+        let kind = K::USE_KIND_MACRO_TO_GENERATE_THIS_FIELD;
+        let item = Arc::new(item);
+        let acquire = {
+            let (item, grant) = (item.clone(), grant.clone());
             Box::new(move || {
-                let item = item.clone();
+                let (item, grant) = (item.clone(), grant.clone());
                 Box::pin(async move {
-                    let (sink, stream) = (item.lock().await)()
+                    if grant.as_ref().map_or(false, |grant| !grant.usable()) {
+                        return Err(CoreError::Revoked);
+                    }
+                    let (sink, stream) = (*item)()
                         .on_to::<IdChannel>()
                         .await
                         .encode::<Cbor>()
                         .split();
-                    Ok(SinkStream::new(sink.sink_map_err(Error::from), stream))
-                })
-            }),
-        );
+                    let sink = sink.sink_map_err(Error::from);
+                    Ok(match grant {
+                        Some(grant) => SinkStream::new(grant.guard(sink), grant.guard(stream)),
+                        None => SinkStream::new(sink, stream),
+                    })
+                }) as Fallible<_, _>
+            })
+        };
+        let (name, methods) = describe(&item);
+        let descriptor = Descriptor {
+            kind,
+            name,
+            methods,
+        };
+        let describe = Box::new(move || {
+            if grant.as_ref().map_or(false, |grant| !grant.usable()) {
+                return None;
+            }
+            Some(descriptor.clone())
+        });
+        self.capabilities
+            .lock()
+            .unwrap()
+            .insert(kind, Capability { acquire, describe });
+        self.notify(Event::Registered(kind));
+    }
+    /// Withdraws the capability of the Kind `K`, returning whether it was
+    /// registered. Objects previously derived from it are unaffected.
+    pub fn unregister<K: Kind>(&mut self) -> bool {
+        self.unregister_hash(K::USE_KIND_MACRO_TO_GENERATE_THIS_FIELD)
+    }
+    /// Withdraws the capability with the provided type hash, returning whether it
+    /// was registered.
+    pub fn unregister_hash(&mut self, ty: [u8; 32]) -> bool {
+        let removed = self.capabilities.lock().unwrap().remove(&ty).is_some();
+        if removed {
+            self.notify(Event::Unregistered(ty));
+        }
+        removed
+    }
+    fn notify(&self, event: Event) {
+        self.listeners
+            .lock()
+            .unwrap()
+            .retain(|listener| listener.unbounded_send(event.clone()).is_ok());
     }
     pub fn into_handle(self) -> Handle {
         Handle(Box::new(self))
@@ -211,6 +662,7 @@ impl Share for Core {
     fn share(&self) -> Self {
         Core {
             capabilities: self.capabilities.clone(),
+            listeners: self.listeners.clone(),
         }
     }
 }
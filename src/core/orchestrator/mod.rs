@@ -2,7 +2,8 @@ use crate::{
     channel::IdChannel,
     core::{
         data::{Checksum, Resource},
-        Constructor, Handle, UnimplementedError,
+        log::Router,
+        Constructor, Handle, Scope, UnimplementedError,
     },
     format::{ApplyDecode, Cbor},
     kind::{using, Fallible, SinkStream, TransportError},
@@ -12,7 +13,10 @@ use crate::{
 };
 
 use anyhow::Error;
-use core::marker::PhantomData;
+use core::{
+    marker::PhantomData,
+    sync::atomic::{AtomicU64, Ordering},
+};
 use futures::SinkExt;
 #[cfg(feature = "core")]
 use futures::StreamExt;
@@ -69,6 +73,10 @@ trait OrchestratorInner {
 #[derive(Kind)]
 pub struct Orchestrator(Shared<dyn OrchestratorInner>);
 
+/// The number of vessels instantiated with a `Log` in this process, from which
+/// their instance ids are assigned.
+static INSTANCES: AtomicU64 = AtomicU64::new(0);
+
 #[derive(Error, Debug, Kind)]
 #[error("instantiate failed: {cause}")]
 pub struct InstantiateError {
@@ -108,6 +116,21 @@ impl Orchestrator {
             Ok(constructor(handle).await?)
         })
     }
+    /// Instantiates `module` as `instantiate` does with the handle of `scope`, on
+    /// which a `Log` capability routing the records of the vessel through
+    /// `router` is registered. Records are attributed to an instance id assigned
+    /// by the orchestrator, which is unique among the vessels instantiated in
+    /// this process.
+    pub fn instantiate_logged<K: Kind>(
+        &self,
+        module: Resource<Module<K>>,
+        mut scope: Scope,
+        router: Router,
+    ) -> Fallible<K, InstantiateError> {
+        let instance = INSTANCES.fetch_add(1, Ordering::Relaxed);
+        scope.register_object(move || router.instance(instance));
+        self.instantiate(module, scope.into_handle())
+    }
     pub fn new() -> Result<Orchestrator, UnimplementedError> {
         #[cfg(feature = "core")]
         return Ok(Orchestrator(Shared::new(Box::new(
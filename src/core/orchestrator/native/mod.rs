@@ -1,7 +1,7 @@
 use super::LocalModule;
 use crate::core::{data::Checksum, spawn};
 use alloc::sync::Arc;
-use core::{ffi::c_void, pin::Pin};
+use core::{cell::Cell, ffi::c_void, pin::Pin};
 use futures::{
     channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender},
     lock,
@@ -10,6 +10,7 @@ use futures::{
 };
 use lazy_static::lazy_static;
 use std::{collections::HashMap, sync::Mutex};
+use thiserror::Error;
 use void::Void;
 use wasmer_runtime::{
     compile, func, imports, memory::MemoryView, wasm::Value, Ctx, Export, Instance as WasmInstance,
@@ -37,10 +38,7 @@ impl NativeInstance {
         use Value::I32;
         let len = data.len() as i32;
         if let I32(ptr) = instance.call("_EXPORT_make_buffer", &[I32(len)]).unwrap()[0] {
-            let view: MemoryView<u8> = self.memory.view();
-            for (idx, byte) in data.into_iter().enumerate() {
-                view[ptr as usize + idx].set(byte)
-            }
+            write_memory(&self.memory, ptr as u32 as usize, &data).expect("bad write");
             instance.call("_EXPORT_input", &[I32(ptr)]).unwrap();
         } else {
             panic!("bad write")
@@ -90,29 +88,69 @@ fn enqueue(cx: &mut Ctx) {
     });
 }
 
-fn output(cx: &mut Ctx, ptr: i32, len: i32) {
-    let mem = cx.memory(0);
-    let mut buffer = vec![0u8; len as usize];
-    let view: MemoryView<u8> = mem.view();
-    let ptr = ptr as usize;
-    for (idx, byte) in buffer.iter_mut().enumerate() {
-        *byte = view[ptr + idx].get();
+/// A range provided by a guest that does not lie within its linear memory.
+#[derive(Error, Debug)]
+#[error("{len} bytes at {ptr} lie outside of guest memory of {size} bytes")]
+struct OutOfBounds {
+    ptr: usize,
+    len: usize,
+    size: usize,
+}
+
+fn cells(view: &MemoryView<u8>, ptr: usize, len: usize) -> Result<&[Cell<u8>], OutOfBounds> {
+    ptr.checked_add(len)
+        .and_then(|end| view.get(ptr..end))
+        .ok_or_else(|| OutOfBounds {
+            ptr,
+            len,
+            size: view.len(),
+        })
+}
+
+// Guest memory is copied in bulk rather than a cell at a time, as payloads such
+// as serialized modules and blobs are frequently large. The range is checked
+// against the size of guest memory before anything is copied.
+fn write_memory(memory: &Memory, ptr: usize, data: &[u8]) -> Result<(), OutOfBounds> {
+    let view: MemoryView<u8> = memory.view();
+    let cells = cells(&view, ptr, data.len())?;
+    // SAFETY: `cells` is exactly `data.len()` cells of guest memory, which stays
+    // mapped while the view is held as the guest is not running, so cannot grow
+    // it. `Cell<u8>` has the layout of `u8` and permits writes through a shared
+    // reference, and `data` is host memory that cannot overlap guest memory.
+    unsafe {
+        core::ptr::copy_nonoverlapping(data.as_ptr(), cells.as_ptr() as *mut u8, data.len());
+    }
+    Ok(())
+}
+
+fn read_memory(memory: &Memory, ptr: usize, len: usize) -> Result<Vec<u8>, OutOfBounds> {
+    let view: MemoryView<u8> = memory.view();
+    let cells = cells(&view, ptr, len)?;
+    let mut buffer = Vec::with_capacity(len);
+    // SAFETY: `cells` is exactly `len` cells of guest memory, which stays mapped
+    // while the view is held as the guest is not running. `buffer` is a new
+    // allocation of capacity `len`, so cannot overlap it, and all `len` bytes are
+    // initialized by the copy before its length is set.
+    unsafe {
+        core::ptr::copy_nonoverlapping(cells.as_ptr() as *const u8, buffer.as_mut_ptr(), len);
+        buffer.set_len(len);
     }
+    Ok(buffer)
+}
+
+// a range outside of guest memory traps the guest that provided it
+fn output(cx: &mut Ctx, ptr: i32, len: i32) -> Result<(), OutOfBounds> {
+    let buffer = read_memory(cx.memory(0), ptr as u32 as usize, len as u32 as usize)?;
     let state = unsafe { Box::from_raw(cx.data as *mut lock::Mutex<State>) };
     spawn(async move {
         state.lock().await.output.send(buffer).await.unwrap();
         Box::leak(state);
     });
+    Ok(())
 }
 
-fn panic(cx: &mut Ctx, ptr: i32, len: i32) {
-    let mem = cx.memory(0);
-    let mut buffer = vec![0u8; len as usize];
-    let view: MemoryView<u8> = mem.view();
-    let ptr = ptr as usize;
-    for (idx, byte) in buffer.iter_mut().enumerate() {
-        *byte = view[ptr + idx].get();
-    }
+fn panic(cx: &mut Ctx, ptr: i32, len: i32) -> Result<(), OutOfBounds> {
+    let buffer = read_memory(cx.memory(0), ptr as u32 as usize, len as u32 as usize)?;
     if let Ok(item) = String::from_utf8(buffer) {
         panic!(item);
     } else {
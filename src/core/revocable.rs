@@ -0,0 +1,182 @@
+use super::{hal::time::Clock, spawn, CoreError};
+
+use alloc::sync::{Arc, Weak};
+use anyhow::Error;
+use core::pin::Pin;
+use futures::{
+    task::{AtomicWaker, Context, Poll},
+    Sink, Stream,
+};
+use std::{sync::Mutex, time::Duration};
+use weak_table::PtrWeakHashSet;
+
+/// Sent to the holder of a capability in place of a frame when the capability is
+/// revoked. No encoded frame is empty, so it cannot be mistaken for one.
+pub(crate) const NOTICE: &[u8] = &[];
+
+/// The terms under which a capability registered with
+/// [`Core::register_revocable`](../struct.Core.html#method.register_revocable)
+/// remains usable. The default terms impose no limit, leaving the capability
+/// usable until it is explicitly revoked.
+#[derive(Clone, Debug, Default)]
+pub struct Terms {
+    /// How long after registration the capability expires. If no `Clock` is
+    /// available the capability expires at once rather than outliving its ttl.
+    pub ttl: Option<Duration>,
+    /// The number of frames holders may send to the capability in total, across
+    /// all acquisitions. Every call made on an object derived from the capability
+    /// sends at least one frame, and most send one per argument in addition. Once
+    /// the budget is exhausted replies to earlier calls are still delivered, but
+    /// the next frame sent revokes the capability.
+    pub frame_budget: Option<u64>,
+}
+
+struct State {
+    revoked: bool,
+    frame_budget: Option<u64>,
+    tasks: PtrWeakHashSet<Weak<AtomicWaker>>,
+}
+
+impl State {
+    fn revoke(&mut self) {
+        self.revoked = true;
+        for task in self.tasks.iter() {
+            task.wake();
+        }
+    }
+    // an exhausted budget revokes the capability only once a further frame is
+    // sent, so that replies to earlier calls get through
+    fn spend(&mut self) -> bool {
+        if self.revoked {
+            return false;
+        }
+        match self.frame_budget {
+            Some(0) => {
+                self.revoke();
+                false
+            }
+            Some(budget) => {
+                self.frame_budget = Some(budget - 1);
+                true
+            }
+            None => true,
+        }
+    }
+}
+
+/// The shared validity of a revocable capability and of every transport derived
+/// from it.
+#[derive(Clone)]
+pub(crate) struct Grant(Arc<Mutex<State>>);
+
+impl Grant {
+    pub(crate) fn new(terms: Terms) -> Self {
+        let grant = Grant(Arc::new(Mutex::new(State {
+            revoked: false,
+            frame_budget: terms.frame_budget,
+            tasks: PtrWeakHashSet::new(),
+        })));
+        if let Some(ttl) = terms.ttl {
+            let state = Arc::downgrade(&grant.0);
+            let sleep = <dyn Clock>::new().map(|clock| clock.sleep(ttl));
+            spawn(async move {
+                if let Ok(sleep) = sleep {
+                    let _ = sleep.await;
+                }
+                if let Some(state) = state.upgrade() {
+                    state.lock().unwrap().revoke();
+                }
+            });
+        }
+        grant
+    }
+
+    pub(crate) fn usable(&self) -> bool {
+        let state = self.0.lock().unwrap();
+        !state.revoked && state.frame_budget != Some(0)
+    }
+
+    /// Wraps one half of a transport to the capability, which fails once the
+    /// capability is revoked or expires.
+    pub(crate) fn guard<T>(&self, inner: T) -> Guarded<T> {
+        let task = Arc::new(AtomicWaker::new());
+        self.0.lock().unwrap().tasks.insert(task.clone());
+        Guarded {
+            inner,
+            grant: self.clone(),
+            task,
+            noticed: false,
+        }
+    }
+}
+
+/// A handle by which the capability registered with
+/// [`Core::register_revocable`](../struct.Core.html#method.register_revocable)
+/// is revoked.
+#[derive(Clone)]
+pub struct Revoker(Grant);
+
+impl Revoker {
+    pub(crate) fn new(grant: Grant) -> Self {
+        Revoker(grant)
+    }
+    /// Revokes the capability. Further attempts to acquire it fail, as do calls
+    /// on any object previously derived from it.
+    pub fn revoke(&self) {
+        (self.0).0.lock().unwrap().revoke();
+    }
+    /// Returns whether the capability has been revoked, has expired or has
+    /// exhausted its budget.
+    pub fn is_revoked(&self) -> bool {
+        !self.0.usable()
+    }
+}
+
+/// One half of a transport to a revocable capability. Once the capability is
+/// revoked the sink refuses frames, and the stream sends the holder a `NOTICE`
+/// and ends.
+pub(crate) struct Guarded<T> {
+    inner: T,
+    grant: Grant,
+    task: Arc<AtomicWaker>,
+    noticed: bool,
+}
+
+impl<T: Stream<Item = Vec<u8>> + Unpin> Stream for Guarded<T> {
+    type Item = Vec<u8>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        self.task.register(cx.waker());
+        if self.grant.0.lock().unwrap().revoked {
+            if self.noticed {
+                return Poll::Ready(None);
+            }
+            self.noticed = true;
+            return Poll::Ready(Some(NOTICE.to_vec()));
+        }
+        Pin::new(&mut self.inner).poll_next(cx)
+    }
+}
+
+impl<I, T: Sink<I, Error = Error> + Unpin> Sink<I> for Guarded<T> {
+    type Error = Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        if self.grant.0.lock().unwrap().revoked {
+            return Poll::Ready(Err(CoreError::Revoked.into()));
+        }
+        Pin::new(&mut self.inner).poll_ready(cx)
+    }
+    fn start_send(mut self: Pin<&mut Self>, item: I) -> Result<(), Self::Error> {
+        if !self.grant.0.lock().unwrap().spend() {
+            return Err(CoreError::Revoked.into());
+        }
+        Pin::new(&mut self.inner).start_send(item)
+    }
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
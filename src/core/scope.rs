@@ -0,0 +1,148 @@
+use super::{Core, CoreError, Descriptor, Event, Handle, HandleInner, Revoker, Terms};
+
+use crate::{
+    kind::{Fallible, SinkStream, Stream},
+    reflect::{Reflected, Trait},
+    Kind,
+};
+
+use alloc::sync::Arc;
+use anyhow::Error;
+use futures::{
+    future::{ready, select, Either},
+    stream, StreamExt,
+};
+use std::collections::HashSet;
+
+/// A handle shared between its original holder and the scopes derived from it.
+pub(crate) struct Delegate(pub(crate) Arc<Box<dyn HandleInner>>);
+
+impl HandleInner for Delegate {
+    fn acquire(&self, ty: [u8; 32]) -> Fallible<SinkStream<Vec<u8>, Error, Vec<u8>>, CoreError> {
+        self.0.acquire(ty)
+    }
+    fn capabilities(&self) -> Fallible<Vec<Descriptor>, CoreError> {
+        self.0.capabilities()
+    }
+    fn acquire_when_available(
+        &self,
+        ty: [u8; 32],
+    ) -> Fallible<SinkStream<Vec<u8>, Error, Vec<u8>>, CoreError> {
+        self.0.acquire_when_available(ty)
+    }
+    fn events(&self) -> Stream<Event> {
+        self.0.events()
+    }
+}
+
+/// An attenuated view of a `Handle`, from which a child handle is created that
+/// exposes only the permitted capabilities of its parent along with any
+/// capabilities registered directly on the scope. Capabilities registered on the
+/// scope take precedence over those of the parent.
+///
+/// Scopes are created with [`Handle::scope`](struct.Handle.html#method.scope)
+/// or, within a vessel, with [`scope`](fn.scope.html), allowing a vessel to
+/// delegate a subset of its own capabilities to the vessels it spawns.
+pub struct Scope {
+    parent: Delegate,
+    permitted: HashSet<[u8; 32]>,
+    overlay: Core,
+}
+
+impl Scope {
+    pub(crate) fn new(parent: Delegate) -> Self {
+        Scope {
+            parent,
+            permitted: HashSet::new(),
+            overlay: Core::new(),
+        }
+    }
+    /// Exposes the parent's capability of the Kind `K`.
+    pub fn permit<K: Kind>(&mut self) {
+        self.permit_hash(K::USE_KIND_MACRO_TO_GENERATE_THIS_FIELD);
+    }
+    /// Exposes the parent's capability with the provided type hash.
+    pub fn permit_hash(&mut self, ty: [u8; 32]) {
+        self.permitted.insert(ty);
+    }
+    /// Registers a capability available only through this scope.
+    pub fn register<K: Kind>(&mut self, item: impl Fn() -> K + Sync + Send + 'static) {
+        self.overlay.register(item);
+    }
+    /// Registers a trait object capability available only through this scope.
+    /// See [`Core::register_object`](struct.Core.html#method.register_object).
+    pub fn register_object<T: Reflected + Trait<T> + ?Sized>(
+        &mut self,
+        item: impl Fn() -> Box<T> + Sync + Send + 'static,
+    ) where
+        Box<T>: Kind,
+    {
+        self.overlay.register_object(item);
+    }
+    /// Registers a revocable capability available only through this scope. See
+    /// [`Core::register_revocable`](struct.Core.html#method.register_revocable).
+    pub fn register_revocable<K: Kind>(
+        &mut self,
+        item: impl Fn() -> K + Sync + Send + 'static,
+        terms: Terms,
+    ) -> Revoker {
+        self.overlay.register_revocable(item, terms)
+    }
+    pub fn into_handle(self) -> Handle {
+        Handle(Box::new(self))
+    }
+}
+
+impl HandleInner for Scope {
+    fn acquire(&self, ty: [u8; 32]) -> Fallible<SinkStream<Vec<u8>, Error, Vec<u8>>, CoreError> {
+        if self.overlay.capabilities.lock().unwrap().contains_key(&ty) {
+            self.overlay.acquire(ty)
+        } else if self.permitted.contains(&ty) {
+            self.parent.acquire(ty)
+        } else {
+            Box::pin(async move { Err(CoreError::Unavailable) })
+        }
+    }
+    fn capabilities(&self) -> Fallible<Vec<Descriptor>, CoreError> {
+        let overlay = self.overlay.capabilities();
+        let parent = self.parent.capabilities();
+        let permitted = self.permitted.clone();
+        Box::pin(async move {
+            let mut descriptors = overlay.await?;
+            for descriptor in parent.await? {
+                if permitted.contains(&descriptor.kind)
+                    && !descriptors.iter().any(|item| item.kind == descriptor.kind)
+                {
+                    descriptors.push(descriptor);
+                }
+            }
+            Ok(descriptors)
+        })
+    }
+    fn acquire_when_available(
+        &self,
+        ty: [u8; 32],
+    ) -> Fallible<SinkStream<Vec<u8>, Error, Vec<u8>>, CoreError> {
+        let overlay = self.overlay.acquire_when_available(ty);
+        if !self.permitted.contains(&ty) {
+            return overlay;
+        }
+        let parent = self.parent.acquire_when_available(ty);
+        Box::pin(async move {
+            match select(overlay, parent).await {
+                Either::Left((channel, _)) | Either::Right((channel, _)) => channel,
+            }
+        })
+    }
+    fn events(&self) -> Stream<Event> {
+        let permitted = self.permitted.clone();
+        let overlay = self.overlay.capabilities.clone();
+        Box::pin(stream::select(
+            self.overlay.events(),
+            self.parent.events().filter(move |event| {
+                let kind = event.kind();
+                ready(permitted.contains(&kind) && !overlay.lock().unwrap().contains_key(&kind))
+            }),
+        ))
+    }
+}
@@ -0,0 +1,295 @@
+use super::Format;
+
+use serde::{de::DeserializeSeed, Serialize};
+
+use crate::kind::Fallible;
+
+use core::{convert::TryInto, marker::PhantomData, pin::Pin};
+use futures::{
+    task::{Context, Poll},
+    Sink, Stream,
+};
+use std::collections::VecDeque;
+
+/// The most representations combined into a single frame. A `BatchSink` holding
+/// this many representations sends them without waiting to be flushed.
+const BATCH_LIMIT: usize = 256;
+
+/// A representation that can carry several others in a single frame.
+pub trait Frame: Sized {
+    /// Combines `items` into one frame.
+    fn join(items: Vec<Self>) -> Self;
+    /// Splits a frame produced by `join` into its items, or returns `None` if the
+    /// frame is malformed.
+    fn split(self) -> Option<Vec<Self>>;
+}
+
+impl Frame for Vec<u8> {
+    fn join(items: Vec<Self>) -> Self {
+        let mut frame = Vec::with_capacity(items.iter().map(|item| item.len() + 4).sum::<usize>());
+        for item in items {
+            frame.extend_from_slice(&(item.len() as u32).to_be_bytes());
+            frame.extend_from_slice(&item);
+        }
+        frame
+    }
+    fn split(self) -> Option<Vec<Self>> {
+        let mut items = vec![];
+        let mut rest = self.as_slice();
+        while !rest.is_empty() {
+            let len = u32::from_be_bytes(rest.get(..4)?.try_into().unwrap()) as usize;
+            // the length is provided by the peer and may overflow a 32-bit usize
+            let end = 4usize.checked_add(len)?;
+            items.push(rest.get(4..end)?.to_vec());
+            rest = &rest[end..];
+        }
+        Some(items)
+    }
+}
+
+impl Frame for String {
+    fn join(items: Vec<Self>) -> Self {
+        let mut frame = String::new();
+        for item in items {
+            frame.push_str(&item.len().to_string());
+            frame.push(':');
+            frame.push_str(&item);
+        }
+        frame
+    }
+    fn split(self) -> Option<Vec<Self>> {
+        let mut items = vec![];
+        let mut rest = self.as_str();
+        while !rest.is_empty() {
+            let colon = rest.find(':')?;
+            let len: usize = rest[..colon].parse().ok()?;
+            rest = &rest[colon + 1..];
+            items.push(rest.get(..len)?.to_owned());
+            rest = &rest[len..];
+        }
+        Some(items)
+    }
+}
+
+/// A format that combines all items of the format `F` that are ready to be sent
+/// at the same time into a single frame, amortizing the per-message overhead of
+/// the transport.
+///
+/// It is used in place of the wrapped format, i.e. `encode::<Batched<Cbor>>()`,
+/// and both ends of a transport must use it.
+pub struct Batched<F>(PhantomData<fn() -> F>);
+
+impl<F: Format + 'static> Format for Batched<F>
+where
+    F::Representation: Frame,
+{
+    type Representation = F::Representation;
+    type Error = F::Error;
+
+    fn name() -> String {
+        format!("batched<{}>", F::name())
+    }
+
+    fn batched() -> bool {
+        true
+    }
+
+    fn serialize<T: Serialize>(item: T) -> Self::Representation {
+        F::serialize(item)
+    }
+
+    fn deserialize<'de, T: DeserializeSeed<'de>>(
+        item: Self::Representation,
+        context: T,
+    ) -> Fallible<T::Value, (Self::Error, Self::Representation)>
+    where
+        T: Sync + Send + 'static,
+    {
+        F::deserialize(item, context)
+    }
+
+    fn batch(items: Vec<Self::Representation>) -> Vec<Self::Representation> {
+        vec![Frame::join(items)]
+    }
+
+    fn unbatch(item: Self::Representation) -> Option<Vec<Self::Representation>> {
+        item.split()
+    }
+}
+
+/// Batches the representations produced by a stream, taking every item that is
+/// ready when polled, up to `BATCH_LIMIT`.
+pub(super) struct Coalesce<F: Format, S> {
+    stream: S,
+    ready: VecDeque<F::Representation>,
+    done: bool,
+}
+
+impl<F: Format, S> Coalesce<F, S> {
+    pub(super) fn new(stream: S) -> Self {
+        Coalesce {
+            stream,
+            ready: VecDeque::new(),
+            done: false,
+        }
+    }
+}
+
+// the buffered representations are never pinned
+impl<F: Format, S: Unpin> Unpin for Coalesce<F, S> {}
+
+impl<F: Format, S: Stream<Item = F::Representation> + Unpin> Stream for Coalesce<F, S> {
+    type Item = F::Representation;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        if let Some(item) = this.ready.pop_front() {
+            return Poll::Ready(Some(item));
+        }
+        let mut items = vec![];
+        while !this.done && items.len() < BATCH_LIMIT {
+            match Pin::new(&mut this.stream).poll_next(cx) {
+                Poll::Ready(Some(item)) => items.push(item),
+                Poll::Ready(None) => this.done = true,
+                Poll::Pending => break,
+            }
+        }
+        if !items.is_empty() {
+            this.ready.extend(F::batch(items));
+        }
+        match this.ready.pop_front() {
+            Some(item) => Poll::Ready(Some(item)),
+            None if this.done => Poll::Ready(None),
+            None => Poll::Pending,
+        }
+    }
+}
+
+/// Batches the representations sent into a sink, holding them until the sink is
+/// flushed or `BATCH_LIMIT` of them are held.
+pub(super) struct BatchSink<F: Format, S> {
+    sink: S,
+    buffer: Vec<F::Representation>,
+    ready: VecDeque<F::Representation>,
+}
+
+impl<F: Format, S> BatchSink<F, S> {
+    pub(super) fn new(sink: S) -> Self {
+        BatchSink {
+            sink,
+            buffer: vec![],
+            ready: VecDeque::new(),
+        }
+    }
+}
+
+// the buffered representations are never pinned
+impl<F: Format, S: Unpin> Unpin for BatchSink<F, S> {}
+
+impl<F: Format, S: Sink<F::Representation> + Unpin> BatchSink<F, S> {
+    fn poll_send(&mut self, cx: &mut Context) -> Poll<Result<(), S::Error>> {
+        if !self.buffer.is_empty() {
+            let items = std::mem::replace(&mut self.buffer, vec![]);
+            self.ready.extend(F::batch(items));
+        }
+        while !self.ready.is_empty() {
+            match Pin::new(&mut self.sink).poll_ready(cx) {
+                Poll::Ready(Ok(())) => {}
+                other => return other,
+            }
+            let item = self.ready.pop_front().unwrap();
+            Pin::new(&mut self.sink).start_send(item)?;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<F: Format, S: Sink<F::Representation> + Unpin> Sink<F::Representation> for BatchSink<F, S> {
+    type Error = S::Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        let this = &mut *self;
+        if this.buffer.len() >= BATCH_LIMIT {
+            match this.poll_send(cx) {
+                Poll::Ready(Ok(())) => {}
+                other => return other,
+            }
+        }
+        Pin::new(&mut this.sink).poll_ready(cx)
+    }
+    fn start_send(mut self: Pin<&mut Self>, item: F::Representation) -> Result<(), Self::Error> {
+        self.buffer.push(item);
+        Ok(())
+    }
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        let this = &mut *self;
+        match this.poll_send(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.sink).poll_flush(cx),
+            other => other,
+        }
+    }
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        let this = &mut *self;
+        match this.poll_send(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.sink).poll_close(cx),
+            other => other,
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::{BatchSink, Batched, Frame, BATCH_LIMIT};
+    use crate::{format::Cbor, kind::Stream, testing::round_trip};
+
+    use futures::{executor::block_on, stream::iter, SinkExt, StreamExt};
+
+    #[test]
+    fn frames_split_into_their_items() {
+        let items: Vec<Vec<u8>> = vec![vec![1, 2], vec![], vec![3]];
+        assert_eq!(Frame::split(Frame::join(items.clone())), Some(items));
+        let items = vec!["a:b".to_owned(), "".to_owned(), "12".to_owned()];
+        assert_eq!(Frame::split(Frame::join(items.clone())), Some(items));
+    }
+
+    #[test]
+    fn truncated_frames_are_malformed() {
+        let mut frame: Vec<u8> = Frame::join(vec![vec![1, 2, 3]]);
+        frame.pop();
+        assert_eq!(Frame::split(frame), None);
+        assert_eq!(Frame::split("3:ab".to_owned()), None);
+    }
+
+    #[test]
+    fn claimed_lengths_beyond_the_frame_are_malformed() {
+        let frame = vec![0xff, 0xff, 0xff, 0xff, 1, 2, 3];
+        assert_eq!(Frame::split(frame), None);
+    }
+
+    #[test]
+    fn batch_sink_holds_at_most_the_limit() {
+        block_on(async {
+            let mut sink = BatchSink::<Batched<Cbor>, _>::new(vec![]);
+            for item in 0..=BATCH_LIMIT {
+                sink.feed(vec![item as u8]).await.unwrap();
+            }
+            assert_eq!(sink.sink.len(), 1);
+            let frame = sink.sink[0].clone();
+            assert_eq!(Frame::split(frame).unwrap().len(), BATCH_LIMIT);
+            assert_eq!(sink.buffer.len(), 1);
+        });
+    }
+
+    #[test]
+    fn batched_streams_round_trip() {
+        block_on(async {
+            let items = BATCH_LIMIT as u32 * 4;
+            let stream: Stream<u32> = Box::pin(iter(0..items));
+            let stream = round_trip::<Batched<Cbor>, _>(stream).await.unwrap();
+            assert_eq!(
+                stream.collect::<Vec<_>>().await,
+                (0..items).collect::<Vec<_>>()
+            );
+        });
+    }
+}
@@ -21,6 +21,10 @@ impl Format for Bincode {
     type Representation = Vec<u8>;
     type Error = serde_bincode::Error;
 
+    fn name() -> String {
+        "bincode".to_owned()
+    }
+
     fn serialize<T: Serialize>(item: T) -> Self::Representation {
         serde_bincode::serialize(&item).unwrap()
     }
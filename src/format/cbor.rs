@@ -20,6 +20,10 @@ impl Format for Cbor {
     type Representation = Vec<u8>;
     type Error = serde_cbor::Error;
 
+    fn name() -> String {
+        "cbor".to_owned()
+    }
+
     fn serialize<T: Serialize>(item: T) -> Self::Representation {
         serde_cbor::to_vec(&item).unwrap()
     }
@@ -0,0 +1,179 @@
+use super::Format;
+
+use serde::{de::DeserializeSeed, Serialize};
+
+use crate::kind::Fallible;
+
+use core::{
+    convert::TryInto,
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+};
+use lz4_flex::block::{compress_with_dict, decompress_with_dict, DecompressError};
+use thiserror::Error;
+
+const RAW: u8 = 0;
+const LZ4: u8 = 1;
+
+/// The parameters of a `Compressed` format. Both ends of a transport must use the
+/// same parameters.
+pub trait Compression: Sync + Send + 'static {
+    /// A name identifying these parameters to peers, which forms part of the name
+    /// of the `Compressed` format using them.
+    const NAME: &'static str;
+    /// Representations shorter than this number of bytes are sent uncompressed,
+    /// as compressing them would cost more than it saves.
+    const THRESHOLD: usize = 256;
+    /// A dictionary of content common to the representations being compressed,
+    /// which substantially improves the compression of small representations.
+    const DICTIONARY: &'static [u8] = &[];
+    /// The largest decompressed size accepted, guarding against representations
+    /// that claim to expand to an excessive size. Each representation is
+    /// decompressed into a buffer of its claimed size, so this bounds the memory a
+    /// peer can make the receiving end allocate with a single small frame.
+    const LIMIT: usize = 1 << 22;
+}
+
+/// The default parameters of a `Compressed` format, which use no dictionary.
+pub struct Standard;
+
+impl Compression for Standard {
+    const NAME: &'static str = "lz4";
+}
+
+/// A format that compresses each representation of the binary format `F` using
+/// LZ4 with the parameters `C`.
+///
+/// This is most effective for large payloads such as the bytes of WASM modules
+/// and `Serde` values. It is used in place of the wrapped format, i.e.
+/// `encode::<Compressed<Cbor>>()`.
+///
+/// For this format to be used the `compressed` feature must be enabled.
+pub struct Compressed<F, C = Standard>(PhantomData<fn() -> (F, C)>);
+
+#[derive(Error)]
+pub enum CompressedError<F: Format> {
+    #[error("malformed compressed representation")]
+    Malformed,
+    #[error("decompressed size of {0} bytes exceeds the limit")]
+    Limit(usize),
+    #[error("decompression failed: {0}")]
+    Decompress(#[source] DecompressError),
+    #[error("{0}")]
+    Format(#[source] F::Error),
+}
+
+impl<F: Format> Debug for CompressedError<F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CompressedError::Malformed => write!(f, "Malformed"),
+            CompressedError::Limit(size) => write!(f, "Limit ({})", size),
+            CompressedError::Decompress(e) => write!(f, "Decompress ({:?})", e),
+            CompressedError::Format(e) => write!(f, "Format ({:?})", e),
+        }
+    }
+}
+
+impl<F: Format<Representation = Vec<u8>> + 'static, C: Compression> Format for Compressed<F, C> {
+    type Representation = Vec<u8>;
+    type Error = CompressedError<F>;
+
+    fn name() -> String {
+        format!("compressed<{}, {}>", F::name(), C::NAME)
+    }
+
+    fn serialize<T: Serialize>(item: T) -> Self::Representation {
+        let data = F::serialize(item);
+        if data.len() < C::THRESHOLD {
+            return raw(data);
+        }
+        let compressed = compress_with_dict(&data, C::DICTIONARY);
+        if compressed.len() + 4 >= data.len() {
+            return raw(data);
+        }
+        let mut representation = Vec::with_capacity(compressed.len() + 5);
+        representation.push(LZ4);
+        representation.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        representation.extend_from_slice(&compressed);
+        representation
+    }
+
+    fn deserialize<'de, T: DeserializeSeed<'de>>(
+        mut item: Self::Representation,
+        context: T,
+    ) -> Fallible<T::Value, (Self::Error, Self::Representation)>
+    where
+        T: Sync + Send + 'static,
+    {
+        Box::pin(async move {
+            let data = match item.first() {
+                Some(&RAW) => item.split_off(1),
+                Some(&LZ4) if item.len() >= 5 => {
+                    let size = u32::from_be_bytes(item[1..5].try_into().unwrap()) as usize;
+                    if size > C::LIMIT {
+                        return Err((CompressedError::Limit(size), item));
+                    }
+                    match decompress_with_dict(&item[5..], size, C::DICTIONARY) {
+                        Ok(data) => data,
+                        Err(e) => return Err((CompressedError::Decompress(e), item)),
+                    }
+                }
+                _ => return Err((CompressedError::Malformed, item)),
+            };
+            // A failed item is handed back uncompressed, which is an equally valid
+            // representation and spares decompressing it again on a retry.
+            F::deserialize(data, context)
+                .await
+                .map_err(|(e, data)| (CompressedError::Format(e), raw(data)))
+        })
+    }
+}
+
+fn raw(data: Vec<u8>) -> Vec<u8> {
+    let mut representation = Vec::with_capacity(data.len() + 1);
+    representation.push(RAW);
+    representation.extend_from_slice(&data);
+    representation
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::{Compressed, CompressedError, Compression, Standard, LZ4};
+    use crate::format::{Cbor, Format};
+
+    use core::marker::PhantomData;
+    use futures::executor::block_on;
+
+    #[test]
+    fn names_are_fixed() {
+        assert_eq!(Compressed::<Cbor>::name(), "compressed<cbor, lz4>");
+    }
+
+    #[test]
+    fn large_items_round_trip() {
+        let item = vec![7u64; 4096];
+        let representation = Compressed::<Cbor>::serialize(&item);
+        assert_eq!(representation[0], LZ4);
+        let received = block_on(Compressed::<Cbor>::deserialize(
+            representation,
+            PhantomData::<Vec<u64>>,
+        ))
+        .ok()
+        .unwrap();
+        assert_eq!(received, item);
+    }
+
+    #[test]
+    fn claimed_sizes_beyond_the_limit_are_rejected() {
+        let mut representation = vec![LZ4];
+        representation.extend_from_slice(&(Standard::LIMIT as u32 + 1).to_be_bytes());
+        representation.extend_from_slice(&[0; 16]);
+        match block_on(Compressed::<Cbor>::deserialize(
+            representation,
+            PhantomData::<Vec<u64>>,
+        )) {
+            Err((CompressedError::Limit(size), _)) => assert_eq!(size, Standard::LIMIT + 1),
+            _ => panic!("a representation claiming an excessive size was accepted"),
+        }
+    }
+}
@@ -17,6 +17,10 @@ impl Format for Json {
     type Representation = String;
     type Error = serde_json::Error;
 
+    fn name() -> String {
+        "json".to_owned()
+    }
+
     fn serialize<T: Serialize>(item: T) -> Self::Representation {
         serde_json::to_string(&item).unwrap()
     }
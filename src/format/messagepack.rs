@@ -0,0 +1,44 @@
+use super::Format;
+
+use serde::{de::DeserializeSeed, Serialize};
+
+use crate::kind::Fallible;
+
+/// A format implementing MessagePack.
+///
+/// MessagePack is a compact, self-describing binary format with implementations
+/// in a wide range of languages, which makes it well suited to communication with
+/// peers not written in Rust. Structs are serialized as maps keyed by field name
+/// for the benefit of such peers. This wraps functionality provided by the
+/// `rmp-serde` crate.
+///
+/// For this format to be used the `messagepack` feature must be enabled.
+pub struct MessagePack;
+
+impl Format for MessagePack {
+    type Representation = Vec<u8>;
+    type Error = rmp_serde::decode::Error;
+
+    fn name() -> String {
+        "messagepack".to_owned()
+    }
+
+    fn serialize<T: Serialize>(item: T) -> Self::Representation {
+        rmp_serde::to_vec_named(&item).unwrap()
+    }
+
+    fn deserialize<'de, T: DeserializeSeed<'de>>(
+        item: Self::Representation,
+        context: T,
+    ) -> Fallible<T::Value, (Self::Error, Self::Representation)>
+    where
+        T: Sync + Send + 'static,
+    {
+        Box::pin(async move {
+            let mut deserializer = rmp_serde::Deserializer::new(item.as_slice());
+            context
+                .deserialize(&mut deserializer)
+                .map_err(|e| (e, item))
+        })
+    }
+}
@@ -11,12 +11,29 @@ pub mod bincode;
 #[cfg(feature = "bincode")]
 #[doc(inline)]
 pub use bincode::Bincode;
+#[cfg(feature = "messagepack")]
+pub mod messagepack;
+#[cfg(feature = "messagepack")]
+#[doc(inline)]
+pub use messagepack::MessagePack;
+#[cfg(feature = "compressed")]
+pub mod compressed;
+#[cfg(feature = "compressed")]
+#[doc(inline)]
+pub use compressed::Compressed;
+pub mod batched;
+#[doc(inline)]
+pub use batched::Batched;
+use batched::{BatchSink, Coalesce};
 
 use predicated_ordered::BufferedPredicatedExt;
 
+use anyhow::anyhow;
 use futures::{
-    channel::mpsc::{unbounded, UnboundedReceiver},
-    future::ok,
+    channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender},
+    future::{ok, ready, Either},
+    stream::iter,
+    task::{Context as FContext, Poll},
     Future as IFuture, FutureExt, Sink as ISink, SinkExt, Stream as IStream, StreamExt,
     TryFutureExt,
 };
@@ -30,7 +47,11 @@ use crate::{
 
 use serde::{de::DeserializeSeed, Serialize};
 
-use core::fmt::{self, Debug, Formatter};
+use core::{
+    fmt::{self, Debug, Formatter},
+    pin::Pin,
+};
+use std::sync::{Arc, Mutex};
 
 use thiserror::Error;
 
@@ -48,6 +69,14 @@ pub trait Format {
     /// The failure condition of this format. This may be encountered during deserialization.
     type Error: ErrorBound;
 
+    /// A name identifying this format to peers when negotiating the format of a
+    /// connection. Defaults to the name of the implementing type.
+    fn name() -> String
+    where
+        Self: Sized,
+    {
+        core::any::type_name::<Self>().to_owned()
+    }
     /// Serializes the provided item.
     fn serialize<T: Serialize>(item: T) -> Self::Representation
     where
@@ -60,6 +89,31 @@ pub trait Format {
     where
         T: Sync + Send + 'static,
         Self: Sized;
+    /// Whether representations that are ready to be sent at the same time are
+    /// combined by `batch`. By default each representation is sent as soon as it
+    /// is ready, and only `Batched` formats batch.
+    fn batched() -> bool
+    where
+        Self: Sized,
+    {
+        false
+    }
+    /// Combines representations that are ready to be sent at the same time into
+    /// the frames actually sent. By default each representation is sent alone.
+    fn batch(items: Vec<Self::Representation>) -> Vec<Self::Representation>
+    where
+        Self: Sized,
+    {
+        items
+    }
+    /// Splits a received frame into the representations combined by `batch`, or
+    /// returns `None` if the frame is malformed.
+    fn unbatch(item: Self::Representation) -> Option<Vec<Self::Representation>>
+    where
+        Self: Sized,
+    {
+        Some(vec![item])
+    }
 }
 
 pub trait ApplyEncode<'de>:
@@ -144,39 +198,67 @@ where
     where
         U::Item: Sync + Send,
     {
-        let shim = U::new_shim();
-        let context = shim.context();
-        let ctx = context.clone();
-        let (sink, stream) = input.split();
-        Box::pin(
-            shim.complete(SinkStream::new(
-                sink.sink_map_err(|_| panic!())
-                    .with::<_, _, _, ()>(|item: U::Item| ok(Self::serialize(item))),
-                stream
-                    .map(move |item| {
-                        let ct = context.clone();
-                        Self::deserialize(item, context.clone())
-                            .or_else(move |(e, item)| {
-                                let context = ct.clone();
-                                let message = format!("{}", e);
-                                let mut data = message.split_whitespace();
-                                if data.next() == Some("ASYNC_WAIT") {
-                                    if let Some(data) = data.next() {
-                                        return context.wait_for(data.to_owned()).then(move |_| {
-                                            Self::deserialize(item, context.clone())
-                                        });
-                                    }
-                                }
-                                panic!(format!("{:?}", e))
-                            })
-                            .unwrap_or_else(|e| panic!(format!("{:?}", e.0)))
-                    })
-                    .buffered_predicated(core::usize::MAX, move |i| ctx.predicate(i)),
-            )),
-        )
+        decode_with::<Self, _, _, U, _>(input, U::new_shim())
     }
 }
 
+/// Decodes `input` with the `Format` `F`, constructing a `Kind` through the
+/// provided shim of the `Target` `U`.
+pub(crate) fn decode_with<'de, F, C, K, U, S>(input: C, shim: S) -> Fallible<K, K::ConstructError>
+where
+    F: Format + 'static,
+    F::Representation: Sync + Send + Clone,
+    C: Sync + Send + UniformStreamSink<F::Representation> + 'static,
+    <C as ISink<F::Representation>>::Error: ErrorBound,
+    K: Kind,
+    U: Target<'de, K> + Sync + Send + 'static,
+    U::Item: Sync + Send,
+    S: Shim<'de, U, K>,
+{
+    let context = shim.context();
+    let ctx = context.clone();
+    let malformed = context.clone();
+    let failure = context.clone();
+    let (sink, stream) = input.split();
+    let sink = sink.sink_map_err(|_| ());
+    let sink = if F::batched() {
+        Either::Left(BatchSink::<F, _>::new(sink))
+    } else {
+        Either::Right(sink)
+    };
+    Box::pin(
+        shim.complete(SinkStream::new(
+            sink.with::<_, _, _, ()>(|item: U::Item| ok(F::serialize(item))),
+            stream
+                // a malformed batch ends the connection, and the forks that end
+                // as a result report why
+                .scan(malformed, |malformed, frame| {
+                    let items = F::unbatch(frame);
+                    if items.is_none() {
+                        malformed.fail(anyhow!("received a malformed batch"));
+                    }
+                    ready(items)
+                })
+                .flat_map(iter)
+                .map(move |item| deserialize_waiting::<F, _>(item, context.clone()))
+                .buffered_predicated(core::usize::MAX, move |i| {
+                    i.as_ref().map_or(false, |i| ctx.predicate(i))
+                })
+                // a frame that cannot be deserialized ends the connection, and
+                // the forks that end as a result report why
+                .scan(failure, |failure, item| {
+                    ready(match item {
+                        Ok(item) => Some(item),
+                        Err((e, _)) => {
+                            failure.fail(anyhow!("failed to decode frame: {}", e));
+                            None
+                        }
+                    })
+                }),
+        )),
+    )
+}
+
 #[derive(Error)]
 pub enum EncodeError<T: Format, I, S: ISink<I>>
 where
@@ -186,6 +268,10 @@ where
     Format(#[source] T::Error),
     #[error("{0}")]
     Sink(#[source] S::Error),
+    #[error("encoder closed after an earlier failure")]
+    Closed,
+    #[error("received a malformed batch")]
+    Malformed,
 }
 
 impl<T: Format, I, S: ISink<I>> Debug for EncodeError<T, I, S>
@@ -199,6 +285,8 @@ where
             match self {
                 EncodeError::Format(e) => format!("Format ({:?})", e),
                 EncodeError::Sink(e) => format!("Sink ({:?})", e),
+                EncodeError::Closed => "Closed".to_owned(),
+                EncodeError::Malformed => "Malformed".to_owned(),
             }
         )
     }
@@ -234,38 +322,119 @@ where
 
     fn encode(input: C) -> Self::Output {
         let ctx = input.context();
-        let (sink, stream) = input.split();
+        let failure_ctx = ctx.clone();
+        let (mut sink, stream) = input.split();
         let (sender, receiver): (_, UnboundedReceiver<<Self as Format>::Representation>) =
             unbounded();
         let predicate_ctx = ctx.clone();
-        let receiver = receiver
+        let mut receiver = receiver
             .map(move |item: <Self as Format>::Representation| {
-                let ct = ctx.clone();
-                Self::deserialize(item, ctx.clone()).or_else(move |(e, item)| {
-                    let context = ct.clone();
-                    let message = format!("{}", e);
-                    let mut data = message.split_whitespace();
-                    if data.next() == Some("ASYNC_WAIT") {
-                        if let Some(data) = data.next() {
-                            return context
-                                .wait_for(data.to_owned())
-                                .then(move |_| Self::deserialize(item, context.clone()));
-                        }
-                    }
-                    panic!(format!("{:?}", e))
-                })
+                deserialize_waiting::<Self, _>(item, ctx.clone())
             })
             .buffered_predicated(core::usize::MAX, move |i| {
-                predicate_ctx.predicate(i.as_ref().unwrap_or_else(|_| panic!()))
+                i.as_ref().map_or(false, |i| predicate_ctx.predicate(i))
             });
-        spawn(
-            receiver
-                .forward(sink.sink_map_err(|e| panic!(format!("{}", e))))
-                .unwrap_or_else(|_| panic!()),
-        );
-        SinkStream::new(
-            sender.sink_map_err(|e| panic!(format!("{}", e))),
-            stream.map(<Self as Format>::serialize),
-        )
+        let failure = Arc::new(Mutex::new(None));
+        let encoder = EncodeSink {
+            sender,
+            failure: failure.clone(),
+        };
+        spawn(async move {
+            while let Some(item) = receiver.next().await {
+                let result = match item {
+                    Ok(item) => sink.send(item).await.map_err(EncodeError::Sink),
+                    Err((e, _)) => {
+                        failure_ctx.fail(anyhow!("failed to decode frame: {}", e));
+                        Err(EncodeError::Format(e))
+                    }
+                };
+                if let Err(e) = result {
+                    *failure.lock().unwrap() = Some(e);
+                    break;
+                }
+            }
+            let _ = sink.close().await;
+        });
+        let stream = stream.map(<Self as Format>::serialize);
+        if Self::batched() {
+            SinkStream::new(encoder, Coalesce::<Self, _>::new(stream))
+        } else {
+            SinkStream::new(encoder, stream)
+        }
     }
 }
+
+/// The input half of an encoder, which reports any failure of the task decoding
+/// its items on the next use.
+struct EncodeSink<T: Format, I, S: ISink<I>>
+where
+    S::Error: ErrorBound,
+{
+    sender: UnboundedSender<T::Representation>,
+    failure: Arc<Mutex<Option<EncodeError<T, I, S>>>>,
+}
+
+impl<T: Format, I, S: ISink<I>> EncodeSink<T, I, S>
+where
+    S::Error: ErrorBound,
+{
+    fn check(&mut self) -> Result<(), EncodeError<T, I, S>> {
+        match self.failure.lock().unwrap().take() {
+            Some(e) => Err(e),
+            None if self.sender.is_closed() => Err(EncodeError::Closed),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T: Format, I, S: ISink<I>> ISink<T::Representation> for EncodeSink<T, I, S>
+where
+    S::Error: ErrorBound,
+{
+    type Error = EncodeError<T, I, S>;
+
+    fn start_send(mut self: Pin<&mut Self>, item: T::Representation) -> Result<(), Self::Error> {
+        self.check()?;
+        for item in T::unbatch(item).ok_or(EncodeError::Malformed)? {
+            self.sender
+                .unbounded_send(item)
+                .map_err(|_| EncodeError::Closed)?;
+        }
+        Ok(())
+    }
+    fn poll_ready(mut self: Pin<&mut Self>, _: &mut FContext) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(self.check())
+    }
+    fn poll_flush(mut self: Pin<&mut Self>, _: &mut FContext) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(self.check())
+    }
+    fn poll_close(mut self: Pin<&mut Self>, _: &mut FContext) -> Poll<Result<(), Self::Error>> {
+        self.sender.close_channel();
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Deserializes `item`, waiting for and then retrying once any state it depends on
+/// that is not yet available, as reported by the `Waiter`, becomes available.
+fn deserialize_waiting<
+    'de,
+    F: Format + 'static,
+    C: DeserializeSeed<'de> + Waiter + Clone + Sync + Send + 'static,
+>(
+    item: F::Representation,
+    context: C,
+) -> Fallible<C::Value, (F::Error, F::Representation)>
+where
+    F::Representation: Sync + Send,
+    C::Value: Sync + Send + 'static,
+{
+    let attempt = context.attempt();
+    Box::pin(
+        F::deserialize(item, attempt.clone()).or_else(move |(e, item)| match attempt.wait() {
+            Some(wait) => {
+                Either::Left(wait.then(move |_| deserialize_waiting::<F, C>(item, context)))
+            }
+            None => Either::Right(ready(Err((e, item)))),
+        }),
+    )
+}
@@ -1,5 +1,5 @@
 use crate::{
-    channel::{Channel, ForkHandle},
+    channel::Channel,
     kind,
     kind::{ConstructResult, DeconstructResult, Future},
     Kind,
@@ -8,13 +8,13 @@ use crate::{
 use core::{mem::MaybeUninit, ptr};
 use futures::{
     future::{ok, try_join_all, Ready},
-    FutureExt, SinkExt, StreamExt, TryFutureExt,
+    SinkExt, StreamExt, TryFutureExt,
 };
 use std::error::Error;
 use thiserror::Error;
 use void::Void;
 
-use super::WrappedError;
+use super::{collections::Elements, WrappedError};
 
 #[kind]
 impl<T: Unpin + Sync + Send + 'static> Kind for [T; 0] {
@@ -44,6 +44,8 @@ pub enum ArrayError<T: Error + 'static> {
     Construct(#[source] T),
     #[error("expected {expected} elements in array, got {got}")]
     Length { got: usize, expected: usize },
+    #[error("received packed elements of a Kind that does not support packing")]
+    Unpacked,
 }
 
 macro_rules! array_impl {
@@ -52,7 +54,7 @@ macro_rules! array_impl {
         impl<T> Kind for [T; $len]
             where T: Kind
         {
-            type ConstructItem = Vec<ForkHandle>;
+            type ConstructItem = Elements<T::ConstructItem>;
             type ConstructError = WrappedError<ArrayError<T::ConstructError>>;
             type ConstructFuture = Future<ConstructResult<Self>>;
             type DeconstructItem = ();
@@ -64,48 +66,54 @@ macro_rules! array_impl {
             ) -> Self::DeconstructFuture {
                 let [$($nn),+] = self;
                 Box::pin(async move {
-                    Ok(channel.send(
-                        vec![
+                    let elements = if let Some(packing) = T::packing() {
+                        Elements::Packed(vec![
+                            $((packing.pack)($nn)),+
+                        ])
+                    } else {
+                        Elements::Forked(vec![
                             $(channel.fork::<T>($nn).await?),+
-                        ]
-                    ).await.map_err(WrappedError::Send)?)
+                        ])
+                    };
+                    Ok(channel.send(elements).await.map_err(WrappedError::Send)?)
                 })
             }
             fn construct<C: Channel<Self::ConstructItem, Self::DeconstructItem>>(
-                channel: C,
+                mut channel: C,
             ) -> Self::ConstructFuture {
-                Box::pin(
-                    channel
-                        .into_future()
-                        .then(move |(item, channel)| async move {
-                            Ok(try_join_all(
-                                item.ok_or(WrappedError::Insufficient {
-                                    got: 0,
-                                    expected: 1
-                                })?
-                                    .into_iter()
-                                    .map(move |item| channel.get_fork::<T>(item)),
-                            ).map_err(ArrayError::Construct).map(|items| {
-                                match items {
-                                    Ok(items) => {
-                                        let len = items.len();
-                                        if len != $len {
-                                            return Err(ArrayError::Length {
-                                                got: len,
-                                                expected: $len
-                                            });
-                                        }
-                                        let mut arr = MaybeUninit::uninit();
-                                        for (i, item) in items.into_iter().enumerate() {
-                                            unsafe { ptr::write((arr.as_mut_ptr() as *mut T).add(i), item) };
-                                        }
-                                        unsafe { Ok(arr.assume_init()) }
-                                    }
-                                    Err(e) => Err(e)
-                                }
-                            }).await?)
-                        })
-                )
+                Box::pin(async move {
+                    let elements = channel.next().await.ok_or(WrappedError::<ArrayError<T::ConstructError>>::Insufficient {
+                        got: 0,
+                        expected: 1
+                    })?;
+                    let items: Vec<T> = match elements {
+                        Elements::Packed(items) => {
+                            let packing = T::packing().ok_or(ArrayError::<T::ConstructError>::Unpacked)?;
+                            items
+                                .into_iter()
+                                .map(packing.unpack)
+                                .collect::<Result<_, _>>()
+                                .map_err(ArrayError::Construct)?
+                        }
+                        Elements::Forked(handles) => try_join_all(
+                            handles
+                                .into_iter()
+                                .map(|item| channel.get_fork::<T>(item)),
+                        ).map_err(ArrayError::Construct).await?
+                    };
+                    let len = items.len();
+                    if len != $len {
+                        return Err(WrappedError::Concrete(ArrayError::Length {
+                            got: len,
+                            expected: $len
+                        }));
+                    }
+                    let mut arr = MaybeUninit::<[T; $len]>::uninit();
+                    for (i, item) in items.into_iter().enumerate() {
+                        unsafe { ptr::write((arr.as_mut_ptr() as *mut T).add(i), item) };
+                    }
+                    unsafe { Ok(arr.assume_init()) }
+                })
             }
         })+
     }
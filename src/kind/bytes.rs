@@ -0,0 +1,54 @@
+use crate::{
+    channel::Channel,
+    kind,
+    kind::{Future, Packing},
+    ConstructResult, DeconstructResult, Kind,
+};
+
+use bytes::Bytes;
+use futures::{SinkExt, StreamExt};
+
+use super::WrappedError;
+
+use void::Void;
+
+/// A shared buffer of binary data. Unlike `Vec<u8>`, which is transported as a
+/// sequence of individual bytes, a `Bytes` is transported as a single byte string
+/// in formats that support them, and clones of it share a single allocation.
+///
+/// This avoids encoding a blob byte by byte, but not copying it: formats own
+/// their representations, so the contents of a `Bytes` are copied into the frame
+/// that carries them and out of it again on the receiving end.
+///
+/// For this `Kind` to be used the `bytes` feature must be enabled.
+#[kind]
+impl Kind for Bytes {
+    type ConstructItem = Bytes;
+    type ConstructError = WrappedError<Void>;
+    type ConstructFuture = Future<ConstructResult<Self>>;
+    type DeconstructItem = ();
+    type DeconstructError = WrappedError<Void>;
+    type DeconstructFuture = Future<DeconstructResult<Self>>;
+    fn deconstruct<C: Channel<Self::DeconstructItem, Self::ConstructItem>>(
+        self,
+        mut channel: C,
+    ) -> Self::DeconstructFuture {
+        Box::pin(async move { Ok(channel.send(self).await.map_err(WrappedError::Send)?) })
+    }
+    fn construct<C: Channel<Self::ConstructItem, Self::DeconstructItem>>(
+        mut channel: C,
+    ) -> Self::ConstructFuture {
+        Box::pin(async move {
+            Ok(channel.next().await.ok_or(WrappedError::Insufficient {
+                got: 0,
+                expected: 1,
+            })?)
+        })
+    }
+    fn packing() -> Option<Packing<Self>> {
+        Some(Packing {
+            pack: |item| item,
+            unpack: |item| Ok(item),
+        })
+    }
+}
@@ -7,20 +7,54 @@ use crate::{
 
 use alloc::collections::{BTreeMap, BTreeSet, BinaryHeap, LinkedList, VecDeque};
 use core::hash::Hash;
-use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+};
+use thiserror::Error;
 
 use futures::{future::try_join_all, SinkExt, StreamExt, TryFutureExt};
 
 use super::WrappedError;
 
+/// The items of a collection as sent over the wire. Elements whose `Kind`
+/// provides a `Packing` are sent inline in a single item, all others are
+/// forked individually.
+#[derive(Serialize, Deserialize)]
+pub enum Elements<T> {
+    Packed(Vec<T>),
+    Forked(Vec<ForkHandle>),
+}
+
+#[derive(Error, Debug)]
+pub enum CollectionError<T: Error + 'static> {
+    #[error("{0}")]
+    Construct(#[source] T),
+    #[error("received packed elements of a Kind that does not support packing")]
+    Unpacked,
+}
+
+#[derive(Error, Debug)]
+pub enum MapError<K: Error + 'static, V: Error + 'static, T: Error + 'static> {
+    #[error("{0}")]
+    Construct(#[source] T),
+    #[error("{0}")]
+    Key(#[source] K),
+    #[error("{0}")]
+    Value(#[source] V),
+    #[error("received packed entries of a Kind that does not support packing")]
+    Unpacked,
+}
+
 macro_rules! iterator_impl {
     ($($ty:ident < T $(: $tbound1:ident $(+ $tbound2:ident)*)* $(, $typaram:ident : $bound:ident)* >),+) => {$(
         #[kind]
         impl<T $(, $typaram)*> Kind for $ty<T $(, $typaram)*>
             where T: Kind $(+ $tbound1 $(+ $tbound2)*)*, $($typaram: $bound,)*
         {
-            type ConstructItem = Vec<ForkHandle>;
-            type ConstructError = WrappedError<T::ConstructError>;
+            type ConstructItem = Elements<T::ConstructItem>;
+            type ConstructError = WrappedError<CollectionError<T::ConstructError>>;
             type ConstructFuture = Future<ConstructResult<Self>>;
             type DeconstructItem = ();
             type DeconstructError = WrappedError<T::DeconstructError>;
@@ -30,26 +64,42 @@ macro_rules! iterator_impl {
                 mut channel: C,
             ) -> Self::DeconstructFuture {
                 Box::pin(async move {
-                    Ok(channel.send(try_join_all(
-                        self.into_iter()
-                            .map(|entry| channel.fork::<T>(entry)),
-                    ).await?).await.map_err(WrappedError::Send)?)
+                    let elements = if let Some(packing) = T::packing() {
+                        Elements::Packed(self.into_iter().map(packing.pack).collect())
+                    } else {
+                        Elements::Forked(try_join_all(
+                            self.into_iter()
+                                .map(|entry| channel.fork::<T>(entry)),
+                        ).await?)
+                    };
+                    Ok(channel.send(elements).await.map_err(WrappedError::Send)?)
                 })
             }
             fn construct<C: Channel<Self::ConstructItem, Self::DeconstructItem>>(
                 mut channel: C,
             ) -> Self::ConstructFuture {
                 Box::pin(async move {
-                    let handles = channel.next().await.ok_or(WrappedError::<T::ConstructError>::Insufficient {
+                    let elements = channel.next().await.ok_or(WrappedError::<CollectionError<T::ConstructError>>::Insufficient {
                         got: 0,
                         expected: 1
                     })?;
-                    Ok(try_join_all(
-                        handles
-                            .into_iter()
-                            .map(|entry| channel.get_fork::<T>(entry)),
-                    )
-                    .map_ok(|vec| vec.into_iter().collect()).await?)
+                    Ok(match elements {
+                        Elements::Packed(items) => {
+                            let packing = T::packing().ok_or(CollectionError::<T::ConstructError>::Unpacked)?;
+                            items
+                                .into_iter()
+                                .map(packing.unpack)
+                                .collect::<Result<Self, _>>()
+                                .map_err(CollectionError::Construct)?
+                        }
+                        Elements::Forked(handles) => try_join_all(
+                            handles
+                                .into_iter()
+                                .map(|entry| channel.get_fork::<T>(entry)),
+                        )
+                        .map_ok(|vec| vec.into_iter().collect())
+                        .map_err(CollectionError::Construct).await?
+                    })
                 })
             }
         }
@@ -72,8 +122,8 @@ macro_rules! map_impl {
             where K: Kind $(+ $tbound1 $(+ $tbound2)*)*,
             V: Kind
         {
-            type ConstructItem = Vec<ForkHandle>;
-            type ConstructError = WrappedError<<(K, V) as Kind>::ConstructError>;
+            type ConstructItem = Elements<(K::ConstructItem, V::ConstructItem)>;
+            type ConstructError = WrappedError<MapError<K::ConstructError, V::ConstructError, <(K, V) as Kind>::ConstructError>>;
             type ConstructFuture = Future<ConstructResult<Self>>;
             type DeconstructItem = ();
             type DeconstructError = WrappedError<<(K, V) as Kind>::DeconstructError>;
@@ -83,26 +133,48 @@ macro_rules! map_impl {
                 mut channel: C,
             ) -> Self::DeconstructFuture {
                 Box::pin(async move {
-                    Ok(channel.send(try_join_all(
-                        self.into_iter()
-                            .map(|entry| channel.fork::<(K, V)>(entry))
-                    ).await?).await.map_err(WrappedError::Send)?)
+                    let elements = if let (Some(key), Some(value)) = (K::packing(), V::packing()) {
+                        Elements::Packed(self.into_iter().map(|(k, v)| ((key.pack)(k), (value.pack)(v))).collect())
+                    } else {
+                        Elements::Forked(try_join_all(
+                            self.into_iter()
+                                .map(|entry| channel.fork::<(K, V)>(entry))
+                        ).await?)
+                    };
+                    Ok(channel.send(elements).await.map_err(WrappedError::Send)?)
                 })
             }
             fn construct<C: Channel<Self::ConstructItem, Self::DeconstructItem>>(
                 mut channel: C,
             ) -> Self::ConstructFuture {
                 Box::pin(async move {
-                    let handles = channel.next().await.ok_or(WrappedError::<<(K, V) as Kind>::ConstructError>::Insufficient {
+                    let elements = channel.next().await.ok_or(WrappedError::<MapError<K::ConstructError, V::ConstructError, <(K, V) as Kind>::ConstructError>>::Insufficient {
                         got: 0,
                         expected: 1
                     })?;
-                    Ok(try_join_all(
-                        handles
-                            .into_iter()
-                            .map(|entry| channel.get_fork::<(K, V)>(entry)),
-                    )
-                    .map_ok(|vec| vec.into_iter().collect()).await?)
+                    Ok(match elements {
+                        Elements::Packed(entries) => {
+                            let (key, value) = match (K::packing(), V::packing()) {
+                                (Some(key), Some(value)) => (key, value),
+                                _ => return Err(WrappedError::Concrete(MapError::Unpacked)),
+                            };
+                            entries
+                                .into_iter()
+                                .map(|(k, v)| {
+                                    (key.unpack)(k)
+                                        .map_err(MapError::Key)
+                                        .and_then(|k| (value.unpack)(v).map(|v| (k, v)).map_err(MapError::Value))
+                                })
+                                .collect::<Result<Self, MapError<_, _, <(K, V) as Kind>::ConstructError>>>()?
+                        }
+                        Elements::Forked(handles) => try_join_all(
+                            handles
+                                .into_iter()
+                                .map(|entry| channel.get_fork::<(K, V)>(entry)),
+                        )
+                        .map_ok(|vec| vec.into_iter().collect())
+                        .map_err(MapError::Construct).await?
+                    })
                 })
             }
         }
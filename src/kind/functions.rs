@@ -1,36 +1,201 @@
 use crate::{
-    channel::{Channel, ForkHandle},
+    channel::{Channel, ChannelError, Fork, ForkHandle},
+    core::spawn,
     kind,
-    kind::{ConstructResult, DeconstructResult, Flatten, Future, WrappedError},
+    kind::{closed, ConstructResult, DeconstructResult, Fallible, Flatten, Future, WrappedError},
     Kind,
 };
 
-use futures::{lock::Mutex, SinkExt, StreamExt, TryFutureExt};
+use anyhow::{anyhow, Error};
+use futures::{
+    channel::oneshot::{channel as oneshot, Sender},
+    future::poll_fn,
+    ready,
+    task::{AtomicWaker, Poll},
+    Future as IFuture, Sink as ISink, SinkExt, Stream as IStream, StreamExt,
+};
 
 use alloc::sync::Arc;
+use core::sync::atomic::{AtomicU32, Ordering};
+use std::{collections::HashMap, sync::Mutex};
 
 use void::Void;
 
+/// The reply to a call, carrying the fork of its return value or a description of
+/// why the call could not be made.
+type Reply = Result<ForkHandle, String>;
+
+/// Forks the output of a call into its reply.
+async fn reply<K: Kind>(
+    output: Result<K, Error>,
+    fork: impl FnOnce(K) -> Fallible<ForkHandle, K::DeconstructError>,
+) -> Reply {
+    match output {
+        Ok(output) => fork(output).await.map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Reconstructs the arguments of a call from their forks, failing if the caller sent
+/// the wrong number of them.
+macro_rules! arguments {
+    ($channel:expr, $handles:expr, $len:expr; $($n:tt $name:ident)*) => {
+        async {
+            if $handles.len() != $len {
+                return Err(anyhow!("expected {} arguments, got {}", $len, $handles.len()));
+            }
+            Ok::<_, Error>(($($channel.get_fork::<$name>($handles[$n]).await?,)*))
+        }
+    };
+}
+
+/// A function channel shared between concurrent invocations. Each call carries a
+/// correlation id, so many calls may be in flight at once and complete in any order.
+/// The underlying channel is only locked for the duration of a single poll.
+struct Pipeline<C> {
+    channel: Mutex<C>,
+    pending: Mutex<HashMap<u32, Sender<Reply>>>,
+    next: AtomicU32,
+    dispatcher: Arc<AtomicWaker>,
+}
+
+impl<C> Drop for Pipeline<C> {
+    fn drop(&mut self) {
+        self.dispatcher.wake();
+    }
+}
+
+impl<C: Fork + Unpin> Pipeline<C> {
+    fn new(channel: C) -> Arc<Self> {
+        Arc::new(Pipeline {
+            channel: Mutex::new(channel),
+            pending: Mutex::new(HashMap::new()),
+            next: AtomicU32::new(0),
+            dispatcher: Arc::new(AtomicWaker::new()),
+        })
+    }
+
+    /// Creates the calling end of a pipeline. Return values are routed to their calls
+    /// by a background task that only holds a weak reference, so the channel is
+    /// closed once the constructed function and all of its pending calls are dropped.
+    fn caller(channel: C) -> Arc<Self>
+    where
+        C: IStream<Item = (u32, Reply)>,
+    {
+        let pipeline = Pipeline::new(channel);
+        let dispatcher = Arc::downgrade(&pipeline);
+        let waker = pipeline.dispatcher.clone();
+        spawn(async move {
+            while let Some((id, reply)) = poll_fn(|cx| {
+                waker.register(cx.waker());
+                dispatcher.upgrade().map_or(Poll::Ready(None), |pipeline| {
+                    let item = pipeline.channel.lock().unwrap().poll_next_unpin(cx);
+                    item
+                })
+            })
+            .await
+            {
+                if let Some(pipeline) = dispatcher.upgrade() {
+                    if let Some(sender) = pipeline.pending.lock().unwrap().remove(&id) {
+                        let _ = sender.send(reply);
+                    }
+                }
+            }
+            if let Some(pipeline) = dispatcher.upgrade() {
+                pipeline.pending.lock().unwrap().clear();
+            }
+        });
+        pipeline
+    }
+
+    fn fork<K: Kind>(&self, kind: K) -> Fallible<ForkHandle, K::DeconstructError> {
+        self.channel.lock().unwrap().fork(kind)
+    }
+
+    fn get_fork<K: Kind>(&self, handle: ForkHandle) -> Fallible<K, K::ConstructError> {
+        self.channel.lock().unwrap().get_fork(handle)
+    }
+
+    fn next<T>(&self) -> impl IFuture<Output = Option<T>> + '_
+    where
+        C: IStream<Item = T>,
+    {
+        poll_fn(move |cx| self.channel.lock().unwrap().poll_next_unpin(cx))
+    }
+
+    fn send<T>(&self, item: T) -> impl IFuture<Output = Result<(), ChannelError>> + '_
+    where
+        C: ISink<T, Error = ChannelError>,
+    {
+        let mut item = Some(item);
+        poll_fn(move |cx| {
+            let mut channel = self.channel.lock().unwrap();
+            if item.is_some() {
+                ready!(channel.poll_ready_unpin(cx))?;
+                channel.start_send_unpin(item.take().unwrap())?;
+            }
+            channel.poll_flush_unpin(cx)
+        })
+    }
+
+    async fn call<A>(&self, arguments: A) -> Result<ForkHandle, Error>
+    where
+        C: ISink<(u32, A), Error = ChannelError>,
+    {
+        let id = self.next.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = oneshot();
+        self.pending.lock().unwrap().insert(id, sender);
+        if let Err(e) = self.send((id, arguments)).await {
+            self.pending.lock().unwrap().remove(&id);
+            return Err(e.into());
+        }
+        receiver
+            .await
+            .map_err(|_| closed(&*self.channel.lock().unwrap(), "the function returned"))?
+            .map_err(Error::msg)
+    }
+
+    /// Serves calls made through `caller` by running each of them concurrently
+    /// with `call`, until the channel closes.
+    async fn serve<A, K: Kind, F: IFuture<Output = Result<K, Error>> + Sync + Send + 'static>(
+        self: Arc<Self>,
+        call: impl Fn(Arc<Self>, A) -> F + Sync + Send,
+    ) where
+        C: IStream<Item = (u32, A)> + ISink<(u32, Reply), Error = ChannelError>,
+        Self: Sync + Send + 'static,
+    {
+        while let Some((id, arguments)) = self.next().await {
+            let channel = self.clone();
+            let output = call(self.clone(), arguments);
+            spawn(async move {
+                let reply = reply(output.await, |output| channel.fork(output)).await;
+                let _ = channel.send((id, reply)).await;
+            });
+        }
+    }
+}
+
 #[kind]
 impl<U: Kind + Flatten> Kind for Box<dyn Fn() -> U + Send + Sync> {
-    type ConstructItem = ForkHandle;
+    type ConstructItem = (u32, Reply);
     type ConstructError = Void;
     type ConstructFuture = Future<ConstructResult<Self>>;
-    type DeconstructItem = ();
-    type DeconstructError = WrappedError<<U as Kind>::DeconstructError>;
+    type DeconstructItem = (u32, ());
+    type DeconstructError = Void;
     type DeconstructFuture = Future<DeconstructResult<Self>>;
 
     fn deconstruct<C: Channel<Self::DeconstructItem, Self::ConstructItem>>(
         self,
-        mut channel: C,
+        channel: C,
     ) -> Self::DeconstructFuture {
         Box::pin(async move {
-            while let Some(()) = channel.next().await {
-                channel
-                    .send(channel.fork((self)()).await?)
-                    .await
-                    .map_err(WrappedError::Send)?;
-            }
+            let function = Arc::new(self);
+            Pipeline::new(channel)
+                .serve(move |_, ()| {
+                    let output = (*function)();
+                    async move { Ok(output) }
+                })
+                .await;
             Ok(())
         })
     }
@@ -38,14 +203,12 @@ impl<U: Kind + Flatten> Kind for Box<dyn Fn() -> U + Send + Sync> {
         channel: C,
     ) -> Self::ConstructFuture {
         Box::pin(async move {
-            let channel = Arc::new(Mutex::new(channel));
+            let channel = Pipeline::caller(channel);
             let closure: Box<dyn Fn() -> U + Send + Sync> = Box::new(move || {
                 let channel = channel.clone();
                 U::flatten(async move {
-                    let mut channel = channel.lock().await;
-                    channel.send(()).unwrap_or_else(|_| panic!()).await;
-                    let handle = channel.next().await.expect("test2");
-                    channel.get_fork(handle).await
+                    let handle = channel.call(()).await?;
+                    Ok::<_, Error>(channel.get_fork(handle).await?)
                 })
             });
             Ok(closure)
@@ -55,11 +218,11 @@ impl<U: Kind + Flatten> Kind for Box<dyn Fn() -> U + Send + Sync> {
 
 #[kind]
 impl<U: Kind + Flatten> Kind for Box<dyn FnMut() -> U + Send + Sync> {
-    type ConstructItem = ForkHandle;
+    type ConstructItem = (u32, Reply);
     type ConstructError = Void;
     type ConstructFuture = Future<ConstructResult<Self>>;
-    type DeconstructItem = ();
-    type DeconstructError = WrappedError<<U as Kind>::DeconstructError>;
+    type DeconstructItem = (u32, ());
+    type DeconstructError = WrappedError<Void>;
     type DeconstructFuture = Future<DeconstructResult<Self>>;
 
     fn deconstruct<C: Channel<Self::DeconstructItem, Self::ConstructItem>>(
@@ -67,9 +230,10 @@ impl<U: Kind + Flatten> Kind for Box<dyn FnMut() -> U + Send + Sync> {
         mut channel: C,
     ) -> Self::DeconstructFuture {
         Box::pin(async move {
-            while let Some(()) = channel.next().await {
+            while let Some((id, ())) = channel.next().await {
+                let reply = reply(Ok((self)()), |output| channel.fork(output)).await;
                 channel
-                    .send(channel.fork((self)()).await?)
+                    .send((id, reply))
                     .await
                     .map_err(WrappedError::Send)?;
             }
@@ -80,14 +244,12 @@ impl<U: Kind + Flatten> Kind for Box<dyn FnMut() -> U + Send + Sync> {
         channel: C,
     ) -> Self::ConstructFuture {
         Box::pin(async move {
-            let channel = Arc::new(Mutex::new(channel));
+            let channel = Pipeline::caller(channel);
             let closure: Box<dyn FnMut() -> U + Send + Sync> = Box::new(move || {
                 let channel = channel.clone();
                 U::flatten(async move {
-                    let mut channel = channel.lock().await;
-                    channel.send(()).unwrap_or_else(|_| panic!()).await;
-                    let handle = channel.next().await.expect("test2");
-                    channel.get_fork(handle).await
+                    let handle = channel.call(()).await?;
+                    Ok::<_, Error>(channel.get_fork(handle).await?)
                 })
             });
             Ok(closure)
@@ -97,11 +259,11 @@ impl<U: Kind + Flatten> Kind for Box<dyn FnMut() -> U + Send + Sync> {
 
 #[kind]
 impl<U: Kind + Flatten> Kind for Box<dyn FnOnce() -> U + Send + Sync> {
-    type ConstructItem = ForkHandle;
+    type ConstructItem = Reply;
     type ConstructError = Void;
     type ConstructFuture = Future<ConstructResult<Self>>;
     type DeconstructItem = ();
-    type DeconstructError = WrappedError<<U as Kind>::DeconstructError>;
+    type DeconstructError = WrappedError<Void>;
     type DeconstructFuture = Future<DeconstructResult<Self>>;
 
     fn deconstruct<C: Channel<Self::DeconstructItem, Self::ConstructItem>>(
@@ -110,10 +272,8 @@ impl<U: Kind + Flatten> Kind for Box<dyn FnOnce() -> U + Send + Sync> {
     ) -> Self::DeconstructFuture {
         Box::pin(async move {
             if let Some(()) = channel.next().await {
-                channel
-                    .send(channel.fork((self)()).await?)
-                    .await
-                    .map_err(WrappedError::Send)?;
+                let reply = reply(Ok((self)()), |output| channel.fork(output)).await;
+                channel.send(reply).await.map_err(WrappedError::Send)?;
             }
             Ok(())
         })
@@ -124,9 +284,13 @@ impl<U: Kind + Flatten> Kind for Box<dyn FnOnce() -> U + Send + Sync> {
         Box::pin(async move {
             let closure: Box<dyn FnOnce() -> U + Send + Sync> = Box::new(move || {
                 U::flatten(async move {
-                    channel.send(()).unwrap_or_else(|_| panic!()).await;
-                    let handle = channel.next().await.expect("test2");
-                    channel.get_fork(handle).await
+                    channel.send(()).await?;
+                    let handle = channel
+                        .next()
+                        .await
+                        .ok_or_else(|| closed(&channel, "the function returned"))?
+                        .map_err(Error::msg)?;
+                    Ok::<_, Error>(channel.get_fork(handle).await?)
                 })
             });
             Ok(closure)
@@ -136,24 +300,25 @@ impl<U: Kind + Flatten> Kind for Box<dyn FnOnce() -> U + Send + Sync> {
 
 #[kind]
 impl<U: Kind + Flatten> Kind for Arc<Box<dyn Fn() -> U + Send + Sync>> {
-    type ConstructItem = ForkHandle;
+    type ConstructItem = (u32, Reply);
     type ConstructError = Void;
     type ConstructFuture = Future<ConstructResult<Self>>;
-    type DeconstructItem = ();
-    type DeconstructError = WrappedError<<U as Kind>::DeconstructError>;
+    type DeconstructItem = (u32, ());
+    type DeconstructError = Void;
     type DeconstructFuture = Future<DeconstructResult<Self>>;
 
     fn deconstruct<C: Channel<Self::DeconstructItem, Self::ConstructItem>>(
         self,
-        mut channel: C,
+        channel: C,
     ) -> Self::DeconstructFuture {
         Box::pin(async move {
-            while let Some(()) = channel.next().await {
-                channel
-                    .send(channel.fork((self)()).await?)
-                    .await
-                    .map_err(WrappedError::Send)?;
-            }
+            let function = self;
+            Pipeline::new(channel)
+                .serve(move |_, ()| {
+                    let output = (*function)();
+                    async move { Ok(output) }
+                })
+                .await;
             Ok(())
         })
     }
@@ -161,14 +326,12 @@ impl<U: Kind + Flatten> Kind for Arc<Box<dyn Fn() -> U + Send + Sync>> {
         channel: C,
     ) -> Self::ConstructFuture {
         Box::pin(async move {
-            let channel = Arc::new(Mutex::new(channel));
+            let channel = Pipeline::caller(channel);
             let closure: Arc<Box<dyn Fn() -> U + Send + Sync>> = Arc::new(Box::new(move || {
                 let channel = channel.clone();
                 U::flatten(async move {
-                    let mut channel = channel.lock().await;
-                    channel.send(()).unwrap_or_else(|_| panic!()).await;
-                    let handle = channel.next().await.expect("test2");
-                    channel.get_fork(handle).await
+                    let handle = channel.call(()).await?;
+                    Ok::<_, Error>(channel.get_fork(handle).await?)
                 })
             }));
             Ok(closure)
@@ -183,29 +346,28 @@ macro_rules! functions_impl {
         impl<U: Kind + Flatten, $($name),+> Kind for Box<dyn Fn($($name),+) -> U + Send + Sync>
             where $($name: Kind),+
         {
-            type ConstructItem = ForkHandle;
+            type ConstructItem = (u32, Reply);
             type ConstructError = Void;
             type ConstructFuture = Future<ConstructResult<Self>>;
-            type DeconstructItem = Vec<ForkHandle>;
+            type DeconstructItem = (u32, Vec<ForkHandle>);
             type DeconstructError = Void;
             type DeconstructFuture = Future<DeconstructResult<Self>>;
 
             fn deconstruct<C: Channel<Self::DeconstructItem, Self::ConstructItem>>(
                 self,
-                mut channel: C,
+                channel: C,
             ) -> Self::DeconstructFuture {
                 Box::pin(async move {
-                    while let Some(handles) = channel.next().await {
-                        channel
-                            .send(
-                                channel
-                                    .fork((self)($(channel.get_fork::<$name>(handles[$n as usize]).await.unwrap()),+))
-                                    .await
-                                    .unwrap(),
-                            )
-                            .unwrap_or_else(|_| panic!())
-                            .await;
-                    }
+                    let function = Arc::new(self);
+                    Pipeline::new(channel)
+                        .serve(move |channel, handles: Vec<ForkHandle>| {
+                            let function = function.clone();
+                            async move {
+                                let arguments = arguments!(channel, handles, $len; $($n $name)+).await?;
+                                Ok((*function)($(arguments.$n),+))
+                            }
+                        })
+                        .await;
                     Ok(())
                 })
             }
@@ -213,18 +375,16 @@ macro_rules! functions_impl {
                 channel: C,
             ) -> Self::ConstructFuture {
                 Box::pin(async move {
-                    let channel = Arc::new(Mutex::new(channel));
+                    let channel = Pipeline::caller(channel);
                     let closure: Box<dyn Fn($($name),+) -> U + Send + Sync> =
                         Box::new(move |$($name),+| {
                             let channel = channel.clone();
                             U::flatten(async move {
-                                let mut channel = channel.lock().await;
                                 let handles = vec![
-                                    $(channel.fork::<$name>($name).await.unwrap()),+
+                                    $(channel.fork::<$name>($name).await?),+
                                 ];
-                                channel.send(handles).unwrap_or_else(|_| panic!()).await;
-                                let handle = channel.next().await.expect("test2");
-                                channel.get_fork(handle).await
+                                let handle = channel.call(handles).await?;
+                                Ok::<_, Error>(channel.get_fork(handle).await?)
                             })
                         });
                     Ok(closure)
@@ -236,11 +396,11 @@ macro_rules! functions_impl {
         impl<U: Kind + Flatten, $($name),+> Kind for Box<dyn FnMut($($name),+) -> U + Send + Sync>
             where $($name: Kind),+
         {
-            type ConstructItem = ForkHandle;
+            type ConstructItem = (u32, Reply);
             type ConstructError = Void;
             type ConstructFuture = Future<ConstructResult<Self>>;
-            type DeconstructItem = Vec<ForkHandle>;
-            type DeconstructError = Void;
+            type DeconstructItem = (u32, Vec<ForkHandle>);
+            type DeconstructError = WrappedError<Void>;
             type DeconstructFuture = Future<DeconstructResult<Self>>;
 
             fn deconstruct<C: Channel<Self::DeconstructItem, Self::ConstructItem>>(
@@ -248,16 +408,15 @@ macro_rules! functions_impl {
                 mut channel: C,
             ) -> Self::DeconstructFuture {
                 Box::pin(async move {
-                    while let Some(handles) = channel.next().await {
+                    while let Some((id, handles)) = channel.next().await {
+                        let output = arguments!(channel, handles, $len; $($n $name)+)
+                            .await
+                            .map(|arguments| (self)($(arguments.$n),+));
+                        let reply = reply(output, |output| channel.fork(output)).await;
                         channel
-                            .send(
-                                channel
-                                    .fork((self)($(channel.get_fork::<$name>(handles[$n as usize]).await.unwrap()),+))
-                                    .await
-                                    .unwrap(),
-                            )
-                            .unwrap_or_else(|_| panic!())
-                            .await;
+                            .send((id, reply))
+                            .await
+                            .map_err(WrappedError::Send)?;
                     }
                     Ok(())
                 })
@@ -266,18 +425,16 @@ macro_rules! functions_impl {
                 channel: C,
             ) -> Self::ConstructFuture {
                 Box::pin(async move {
-                    let channel = Arc::new(Mutex::new(channel));
+                    let channel = Pipeline::caller(channel);
                     let closure: Box<dyn FnMut($($name),+) -> U + Send + Sync> =
                         Box::new(move |$($name),+| {
                             let channel = channel.clone();
                             U::flatten(async move {
-                                let mut channel = channel.lock().await;
                                 let handles = vec![
-                                    $(channel.fork::<$name>($name).await.unwrap()),+
+                                    $(channel.fork::<$name>($name).await?),+
                                 ];
-                                channel.send(handles).unwrap_or_else(|_| panic!()).await;
-                                let handle = channel.next().await.expect("test2");
-                                channel.get_fork(handle).await
+                                let handle = channel.call(handles).await?;
+                                Ok::<_, Error>(channel.get_fork(handle).await?)
                             })
                         });
                     Ok(closure)
@@ -289,11 +446,11 @@ macro_rules! functions_impl {
         impl<U: Kind + Flatten, $($name),+> Kind for Box<dyn FnOnce($($name),+) -> U + Send + Sync>
             where $($name: Kind),+
         {
-            type ConstructItem = ForkHandle;
+            type ConstructItem = Reply;
             type ConstructError = Void;
             type ConstructFuture = Future<ConstructResult<Self>>;
             type DeconstructItem = Vec<ForkHandle>;
-            type DeconstructError = Void;
+            type DeconstructError = WrappedError<Void>;
             type DeconstructFuture = Future<DeconstructResult<Self>>;
 
             fn deconstruct<C: Channel<Self::DeconstructItem, Self::ConstructItem>>(
@@ -301,16 +458,13 @@ macro_rules! functions_impl {
                 mut channel: C,
             ) -> Self::DeconstructFuture {
                 Box::pin(async move {
-                    let handles = channel.next().await.unwrap();
-                    channel
-                        .send(
-                            channel
-                                .fork((self)($(channel.get_fork::<$name>(handles[$n as usize]).await.unwrap()),+))
-                                .await
-                                .unwrap(),
-                        )
-                        .unwrap_or_else(|_| panic!())
-                        .await;
+                    if let Some(handles) = channel.next().await {
+                        let output = arguments!(channel, handles, $len; $($n $name)+)
+                            .await
+                            .map(|arguments| (self)($(arguments.$n),+));
+                        let reply = reply(output, |output| channel.fork(output)).await;
+                        channel.send(reply).await.map_err(WrappedError::Send)?;
+                    }
                     Ok(())
                 })
             }
@@ -322,11 +476,15 @@ macro_rules! functions_impl {
                         Box::new(move |$($name),+| {
                             U::flatten(async move {
                                 let handles = vec![
-                                    $(channel.fork::<$name>($name).await.unwrap()),+
+                                    $(channel.fork::<$name>($name).await?),+
                                 ];
-                                channel.send(handles).unwrap_or_else(|_| panic!()).await;
-                                let handle = channel.next().await.expect("test2");
-                                channel.get_fork(handle).await
+                                channel.send(handles).await?;
+                                let handle = channel
+                                    .next()
+                                    .await
+                                    .ok_or_else(|| closed(&channel, "the function returned"))?
+                                    .map_err(Error::msg)?;
+                                Ok::<_, Error>(channel.get_fork(handle).await?)
                             })
                         });
                     Ok(closure)
@@ -338,27 +496,27 @@ macro_rules! functions_impl {
         impl<U: Kind + Flatten, $($name),+> Kind for Arc<Box<dyn Fn($($name),+) -> U + Send + Sync>>
             where $($name: Kind),+
         {
-            type ConstructItem = ForkHandle;
+            type ConstructItem = (u32, Reply);
             type ConstructError = Void;
             type ConstructFuture = Future<ConstructResult<Self>>;
-            type DeconstructItem = Vec<ForkHandle>;
+            type DeconstructItem = (u32, Vec<ForkHandle>);
             type DeconstructError = Void;
             type DeconstructFuture = Future<DeconstructResult<Self>>;
 
             fn deconstruct<C: Channel<Self::DeconstructItem, Self::ConstructItem>>(
                 self,
-                mut channel: C,
+                channel: C,
             ) -> Self::DeconstructFuture {
                 Box::pin(async move {
-                    let handles = channel.next().await.unwrap();
-                    channel
-                        .send(
-                            channel
-                                .fork((self)($(channel.get_fork::<$name>(handles[$n as usize]).await.unwrap()),+))
-                                .await
-                                .unwrap(),
-                        )
-                        .unwrap_or_else(|_| panic!())
+                    let function = self;
+                    Pipeline::new(channel)
+                        .serve(move |channel, handles: Vec<ForkHandle>| {
+                            let function = function.clone();
+                            async move {
+                                let arguments = arguments!(channel, handles, $len; $($n $name)+).await?;
+                                Ok((*function)($(arguments.$n),+))
+                            }
+                        })
                         .await;
                     Ok(())
                 })
@@ -367,18 +525,16 @@ macro_rules! functions_impl {
                 channel: C,
             ) -> Self::ConstructFuture {
                 Box::pin(async move {
-                    let channel = Arc::new(Mutex::new(channel));
+                    let channel = Pipeline::caller(channel);
                     let closure: Arc<Box<dyn Fn($($name),+) -> U + Send + Sync>> =
                         Arc::new(Box::new(move |$($name),+| {
                             let channel = channel.clone();
                             U::flatten(async move {
-                                let mut channel = channel.lock().await;
                                 let handles = vec![
-                                    $(channel.fork::<$name>($name).await.unwrap()),+
+                                    $(channel.fork::<$name>($name).await?),+
                                 ];
-                                channel.send(handles).unwrap_or_else(|_| panic!()).await;
-                                let handle = channel.next().await.expect("test2");
-                                channel.get_fork(handle).await
+                                let handle = channel.call(handles).await?;
+                                Ok::<_, Error>(channel.get_fork(handle).await?)
                             })
                         }));
                     Ok(closure)
@@ -1,44 +1,80 @@
 use crate::{
     channel::{Channel, ForkHandle},
     kind,
-    kind::Future,
+    kind::{Fallible, Future},
     ConstructResult, DeconstructResult, Kind,
 };
 
-use futures::{SinkExt, StreamExt};
+use futures::{
+    future::{select, Either},
+    SinkExt, StreamExt,
+};
 
-use super::WrappedError;
+use super::{closed, Cancel, TransportError, WrappedError};
 
+/// A `Future` is a `Kind` only if it resolves to a `Result` whose error can
+/// represent a `TransportError`, such as an `Infallible`.
+///
+/// A plain `Future<T>` was formerly a `Kind`, but its constructed side had no way
+/// to report that the channel failed before the output arrived other than to
+/// panic or never resolve. As `Fallible<T, E>` is itself a `Future`, the two
+/// implementations cannot coexist, so a `Future<T>` should be sent as an
+/// `Infallible<T>` instead, resolving to `Ok` where it formerly resolved to a bare
+/// value.
+/// ```ignore
+/// // formerly
+/// let future: Future<u32> = Box::pin(async { 5 });
+/// // now
+/// let future: Infallible<u32> = Box::pin(async { Ok(5) });
+/// ```
+/// A future with an error of its own converts that error from a `TransportError`
+/// by implementing `From<TransportError>` for it, or by wrapping it in an error
+/// that does.
 #[kind]
-impl<T> Kind for Future<T>
+impl<T, E> Kind for Fallible<T, E>
 where
     T: Kind,
+    E: Kind + From<TransportError>,
 {
     type ConstructItem = ForkHandle;
-    type ConstructError = T::ConstructError;
+    type ConstructError = <Result<T, E> as Kind>::ConstructError;
     type ConstructFuture = Future<ConstructResult<Self>>;
     type DeconstructItem = ();
-    type DeconstructError = WrappedError<T::DeconstructError>;
+    type DeconstructError = WrappedError<<Result<T, E> as Kind>::DeconstructError>;
     type DeconstructFuture = Future<DeconstructResult<Self>>;
     fn deconstruct<C: Channel<Self::DeconstructItem, Self::ConstructItem>>(
         self,
         mut channel: C,
     ) -> Self::DeconstructFuture {
         Box::pin(async move {
+            let item = match select(self, channel.next()).await {
+                Either::Left((item, _)) => item,
+                Either::Right(_) => return Ok(()),
+            };
             Ok(channel
-                .send(channel.fork(self.await).await?)
+                .send(channel.fork(item).await?)
                 .await
                 .map_err(WrappedError::Send)?)
         })
     }
     fn construct<C: Channel<Self::ConstructItem, Self::DeconstructItem>>(
-        mut channel: C,
+        channel: C,
     ) -> Self::ConstructFuture {
         Box::pin(async move {
+            let mut channel = Cancel::new(channel, ());
             Ok(Box::pin(async move {
-                let handle = channel.next().await.unwrap();
-                channel.get_fork::<T>(handle).await.unwrap()
-            }) as Future<T>)
+                let handle = channel
+                    .channel()
+                    .next()
+                    .await
+                    .ok_or_else(|| E::from(closed(channel.channel(), "the future resolved")))?;
+                channel.disarm();
+                channel
+                    .channel()
+                    .get_fork::<Result<T, E>>(handle)
+                    .await
+                    .map_err(|e| E::from(TransportError::new(e.into())))?
+            }) as Fallible<T, E>)
         })
     }
 }
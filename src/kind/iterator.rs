@@ -1,13 +1,11 @@
-use futures::{future::try_join_all, SinkExt, StreamExt};
+use futures::{future::try_join_all, SinkExt, StreamExt, TryFutureExt};
 
-use crate::{
-    channel::{Channel, ForkHandle},
-    kind,
-    kind::Future,
-    ConstructResult, DeconstructResult, Kind,
-};
+use crate::{channel::Channel, kind, kind::Future, ConstructResult, DeconstructResult, Kind};
 
-use super::{using, AsKind, WrappedError};
+use super::{
+    collections::{CollectionError, Elements},
+    using, AsKind, WrappedError,
+};
 
 use core::{iter::FromIterator, ops::Deref};
 
@@ -105,8 +103,9 @@ where
     <T as IntoIterator>::Item: Kind,
     T::IntoIter: Unpin + Sync + Send,
 {
-    type ConstructItem = Vec<ForkHandle>;
-    type ConstructError = WrappedError<<<T as IntoIterator>::Item as Kind>::ConstructError>;
+    type ConstructItem = Elements<<<T as IntoIterator>::Item as Kind>::ConstructItem>;
+    type ConstructError =
+        WrappedError<CollectionError<<<T as IntoIterator>::Item as Kind>::ConstructError>>;
     type ConstructFuture = Future<ConstructResult<Self>>;
     type DeconstructItem = ();
     type DeconstructError = WrappedError<<<T as IntoIterator>::Item as Kind>::DeconstructError>;
@@ -117,8 +116,10 @@ where
         mut channel: C,
     ) -> Self::DeconstructFuture {
         Box::pin(async move {
-            Ok(channel
-                .send(
+            let elements = if let Some(packing) = <<T as IntoIterator>::Item as Kind>::packing() {
+                Elements::Packed(self.0.into_iter().map(packing.pack).collect())
+            } else {
+                Elements::Forked(
                     try_join_all(
                         self.0
                             .into_iter()
@@ -126,28 +127,41 @@ where
                     )
                     .await?,
                 )
-                .await
-                .map_err(WrappedError::Send)?)
+            };
+            Ok(channel.send(elements).await.map_err(WrappedError::Send)?)
         })
     }
     fn construct<C: Channel<Self::ConstructItem, Self::DeconstructItem>>(
         mut channel: C,
     ) -> Self::ConstructFuture {
         Box::pin(async move {
-            let handles = channel.next().await.ok_or(WrappedError::Insufficient {
+            let elements = channel.next().await.ok_or(WrappedError::<
+                CollectionError<<<T as IntoIterator>::Item as Kind>::ConstructError>,
+            >::Insufficient {
                 got: 0,
                 expected: 1,
             })?;
-            Ok(Iterator(
-                try_join_all(
+            Ok(Iterator(match elements {
+                Elements::Packed(items) => {
+                    let packing = <<T as IntoIterator>::Item as Kind>::packing().ok_or(CollectionError::<
+                        <<T as IntoIterator>::Item as Kind>::ConstructError,
+                    >::Unpacked)?;
+                    items
+                        .into_iter()
+                        .map(packing.unpack)
+                        .collect::<Result<T, _>>()
+                        .map_err(CollectionError::Construct)?
+                }
+                Elements::Forked(handles) => try_join_all(
                     handles
                         .into_iter()
                         .map(|entry| channel.get_fork::<<T as IntoIterator>::Item>(entry)),
                 )
+                .map_err(CollectionError::Construct)
                 .await?
                 .into_iter()
                 .collect(),
-            ))
+            }))
         })
     }
 }
@@ -1,4 +1,6 @@
 mod array;
+#[cfg(feature = "bytes")]
+mod bytes;
 mod collections;
 mod default;
 mod error;
@@ -23,15 +25,20 @@ pub use default::Default;
 pub use iterator::Iterator;
 pub use sink_stream::SinkStream;
 
-use anyhow::Error;
+use anyhow::{anyhow, Error};
 use core::pin::Pin;
 use futures::{
-    stream::once, Future as IFuture, FutureExt, Sink as ISink, Stream as IStream, StreamExt,
+    stream::once, Future as IFuture, FutureExt, Sink as ISink, SinkExt, Stream as IStream,
+    StreamExt,
 };
-use std::error::Error as StdError;
+use std::{error::Error as StdError, sync::Arc};
 use thiserror::Error;
 
-use crate::{channel::ChannelError, Kind};
+use crate::{
+    channel::{ChannelError, Fork},
+    core::spawn,
+    Kind,
+};
 
 #[derive(Error, Kind, Debug)]
 #[error("transport error: {cause}")]
@@ -41,22 +48,58 @@ pub struct TransportError {
 }
 
 impl TransportError {
-    fn new(cause: Error) -> Self {
+    pub(crate) fn new(cause: Error) -> Self {
         TransportError { cause }
     }
+    /// Returns the cause of the failure if it is an error of type `E`, including
+    /// when it is the recorded failure of the underlying connection.
+    pub(crate) fn cause<E: StdError + Sync + Send + 'static>(&self) -> Option<&E> {
+        match self.cause.downcast_ref::<Failure>() {
+            Some(Failure(cause)) => cause.downcast_ref(),
+            None => self.cause.downcast_ref(),
+        }
+    }
+}
+
+/// The failure of a connection, shared by every fork that ends because of it.
+#[derive(Error, Debug)]
+#[error("{0:#}")]
+pub(crate) struct Failure(pub(crate) Arc<Error>);
+
+/// Describes why `channel` ended before `what`, reporting the failure of the
+/// underlying connection if it failed rather than closed.
+pub(crate) fn closed(channel: &impl Fork, what: &str) -> TransportError {
+    TransportError::new(
+        channel
+            .failure()
+            .unwrap_or_else(|| anyhow!("channel closed before {}", what)),
+    )
 }
 
+/// A boxed future. Only futures of a `Result` whose error converts from a
+/// `TransportError`, i.e. `Fallible`s, are `Kind`s.
 pub type Future<T> = Pin<Box<dyn IFuture<Output = T> + Sync + Send>>;
 pub type Fallible<T, E> = Future<Result<T, E>>;
 pub type Stream<T> = Pin<Box<dyn IStream<Item = T> + Sync + Send>>;
 pub type Infallible<T> = Fallible<T, TransportError>;
 pub type Sink<T, E> = Pin<Box<dyn ISink<T, Error = E> + Sync + Send>>;
 
+/// The number of items a `Stream` or `Sink` may have in flight before the
+/// consuming side grants further credit.
+pub(crate) const CREDIT_WINDOW: u32 = 16;
+
 /// The result of reconstructing a Kind.
 pub type ConstructResult<K> = Result<K, <K as Kind>::ConstructError>;
 /// The result of deconstructing a Kind.
 pub type DeconstructResult<K> = Result<(), <K as Kind>::DeconstructError>;
 
+/// Channel-free conversions for a `Kind` that is communicated as a single
+/// `ConstructItem`, as returned by `Kind::packing`.
+pub struct Packing<K: Kind> {
+    pub pack: fn(K) -> K::ConstructItem,
+    pub unpack: fn(K::ConstructItem) -> ConstructResult<K>,
+}
+
 pub trait Flatten: Sized {
     fn flatten<
         E: 'static + Sync + Send + Into<Error>,
@@ -104,6 +147,39 @@ impl<U: From<TransportError>, T> Flatten for Stream<Result<T, U>> {
     }
 }
 
+/// Holds the channel of a constructed `Kind` and sends `signal` on it if dropped
+/// before being disarmed, allowing the deconstructing side to stop work nobody
+/// is waiting on.
+pub(crate) struct Cancel<T: Sync + Send + 'static, C: ISink<T> + Sync + Send + Unpin + 'static> {
+    channel: Option<C>,
+    signal: Option<T>,
+}
+
+impl<T: Sync + Send + 'static, C: ISink<T> + Sync + Send + Unpin + 'static> Cancel<T, C> {
+    pub(crate) fn new(channel: C, signal: T) -> Self {
+        Cancel {
+            channel: Some(channel),
+            signal: Some(signal),
+        }
+    }
+    pub(crate) fn channel(&mut self) -> &mut C {
+        self.channel.as_mut().unwrap()
+    }
+    pub(crate) fn disarm(&mut self) {
+        self.signal.take();
+    }
+}
+
+impl<T: Sync + Send + 'static, C: ISink<T> + Sync + Send + Unpin + 'static> Drop for Cancel<T, C> {
+    fn drop(&mut self) {
+        if let (Some(mut channel), Some(signal)) = (self.channel.take(), self.signal.take()) {
+            spawn(async move {
+                let _ = channel.send(signal).await;
+            });
+        }
+    }
+}
+
 pub trait AsKindMarker {}
 
 #[derive(Error, Debug)]
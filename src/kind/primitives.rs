@@ -8,7 +8,12 @@ use std::{
     time::{Duration, SystemTime},
 };
 
-use crate::{channel::Channel, kind, kind::Future, ConstructResult, DeconstructResult, Kind};
+use crate::{
+    channel::Channel,
+    kind,
+    kind::{Future, Packing},
+    ConstructResult, DeconstructResult, Kind,
+};
 
 use futures::{SinkExt, StreamExt};
 
@@ -46,6 +51,12 @@ macro_rules! primitive_impl {
                     })?)
                 })
             }
+            fn packing() -> Option<Packing<Self>> {
+                Some(Packing {
+                    pack: |item| item,
+                    unpack: Ok,
+                })
+            }
         }
     )+};
 }
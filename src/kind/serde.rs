@@ -2,7 +2,12 @@ use serde::{de::DeserializeOwned, Serialize};
 
 use futures::{SinkExt, StreamExt};
 
-use crate::{channel::Channel, kind, kind::Future, ConstructResult, DeconstructResult, Kind};
+use crate::{
+    channel::Channel,
+    kind,
+    kind::{Future, Packing},
+    ConstructResult, DeconstructResult, Kind,
+};
 
 use super::{using, AsKind, WrappedError};
 
@@ -71,4 +76,10 @@ impl<T: Serialize + DeserializeOwned + Sync + Send + Unpin + 'static> Kind for S
             )?))
         })
     }
+    fn packing() -> Option<Packing<Self>> {
+        Some(Packing {
+            pack: |item| item.0,
+            unpack: |item| Ok(Serde(item)),
+        })
+    }
 }
@@ -1,92 +1,203 @@
 use crate::{
     channel::{Channel, ForkHandle},
     kind,
-    kind::{Future, Sink},
+    kind::{Fallible, Future, Sink},
     ConstructResult, DeconstructResult, Kind,
 };
 
 use futures::{
-    future::ready,
+    future::{poll_fn, ready},
     lock::Mutex,
-    task::{Context, Poll},
+    task::{noop_waker_ref, Context, Poll},
     Sink as ISink, SinkExt, StreamExt,
 };
+use serde::{Deserialize, Serialize};
 
-use super::WrappedError;
+use super::{closed, TransportError, WrappedError, CREDIT_WINDOW};
 
 use alloc::sync::Arc;
 use core::{marker::PhantomData, pin::Pin};
 
 use void::Void;
 
-pub struct KindSink<T: Kind, E: Kind, C: Channel<ForkHandle, ForkHandle>> {
-    channel: Arc<Mutex<C>>,
-    _marker: PhantomData<(T, E)>,
-    item: Future<()>,
+/// Sent from the side holding the underlying sink to the side writing into it.
+#[derive(Serialize, Deserialize)]
+pub enum Feedback {
+    Credit(u32),
+    Error(ForkHandle),
 }
 
-impl<T: Kind, E: Kind, C: Channel<ForkHandle, ForkHandle>> ISink<T> for KindSink<T, E, C> {
+struct SinkState<E: Kind, C> {
+    channel: C,
+    credits: u32,
+    closed: bool,
+    failure: Option<Fallible<E, E::ConstructError>>,
+    error: Option<E>,
+}
+
+impl<E: Kind + From<TransportError>, C: Channel<Feedback, ForkHandle>> SinkState<E, C> {
+    /// Takes all feedback that is available without waiting, keeping the first
+    /// error of the underlying sink until it is reported.
+    fn drain(&mut self, cx: &mut Context) {
+        loop {
+            if let Some(failure) = self.failure.as_mut() {
+                match failure.as_mut().poll(cx) {
+                    Poll::Ready(error) => {
+                        self.failure = None;
+                        let error =
+                            error.unwrap_or_else(|e| E::from(TransportError::new(e.into())));
+                        self.error.get_or_insert(error);
+                    }
+                    Poll::Pending => return,
+                }
+            }
+            if self.closed {
+                return;
+            }
+            match self.channel.poll_next_unpin(cx) {
+                Poll::Ready(Some(Feedback::Credit(credits))) => self.credits += credits,
+                Poll::Ready(Some(Feedback::Error(handle))) => {
+                    self.failure = Some(self.channel.get_fork::<E>(handle));
+                }
+                Poll::Ready(None) => self.closed = true,
+                Poll::Pending => return,
+            }
+        }
+    }
+
+    fn reserve(&mut self, cx: &mut Context) -> Poll<Result<(), E>> {
+        self.drain(cx);
+        if let Some(error) = self.error.take() {
+            Poll::Ready(Err(error))
+        } else if self.credits > 0 {
+            self.credits -= 1;
+            Poll::Ready(Ok(()))
+        } else if self.closed && self.failure.is_none() {
+            Poll::Ready(Err(E::from(closed(&self.channel, "the item was sent"))))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+pub struct KindSink<T: Kind, E: Kind, C: Channel<Feedback, ForkHandle>> {
+    state: Arc<Mutex<SinkState<E, C>>>,
+    _marker: PhantomData<T>,
+    item: Future<Result<(), E>>,
+}
+
+impl<T: Kind, E: Kind + From<TransportError>, C: Channel<Feedback, ForkHandle>> KindSink<T, E, C> {
+    // the state is only held by an item in flight, so once that item completes it
+    // is always available here
+    fn take_error(&self, cx: &mut Context) -> Option<E> {
+        let mut state = self.state.try_lock()?;
+        state.drain(cx);
+        state.error.take()
+    }
+
+    fn poll_item(&mut self, cx: &mut Context) -> Poll<Result<(), E>> {
+        match self.item.as_mut().poll(cx) {
+            Poll::Ready(result) => {
+                self.item = Box::pin(ready(Ok(())));
+                result?;
+                Poll::Ready(self.take_error(cx).map_or(Ok(()), Err))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<T: Kind, E: Kind + From<TransportError>, C: Channel<Feedback, ForkHandle>> ISink<T>
+    for KindSink<T, E, C>
+{
     type Error = E;
 
     fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
-        let poll = self.item.as_mut().poll(cx).map(Ok);
-        if let Poll::Ready(_) = poll {
-            self.item = Box::pin(ready(()));
-        }
-        poll
+        self.poll_item(cx)
     }
 
     fn start_send(mut self: Pin<&mut Self>, item: T) -> Result<(), Self::Error> {
-        let channel = self.channel.clone();
+        if let Some(error) = self.take_error(&mut Context::from_waker(noop_waker_ref())) {
+            return Err(error);
+        }
+        let state = self.state.clone();
         self.item = Box::pin(async move {
-            let mut channel = channel.lock().await;
-            let handle = channel.fork(item).await.unwrap();
-            channel.send(handle).await.unwrap_or_else(|_| panic!());
+            let mut state = state.lock().await;
+            poll_fn(|cx| state.reserve(cx)).await?;
+            let handle = state
+                .channel
+                .fork(item)
+                .await
+                .map_err(|e| E::from(TransportError::new(e.into())))?;
+            state
+                .channel
+                .send(handle)
+                .await
+                .map_err(|e| E::from(TransportError::new(e.into())))
         });
         Ok(())
     }
 
     fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
-        let poll = self.item.as_mut().poll(cx).map(Ok);
-        if let Poll::Ready(_) = poll {
-            self.item = Box::pin(ready(()));
-        }
-        poll
+        self.poll_item(cx)
     }
 
     fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
-        let poll = self.item.as_mut().poll(cx).map(Ok);
-        if let Poll::Ready(_) = poll {
-            self.item = Box::pin(ready(()));
-        }
-        poll
+        self.poll_item(cx)
     }
 }
 
+/// A `Sink` is a `Kind` only if its error can represent a `TransportError`, which
+/// its constructed side reports when an item cannot be delivered because the
+/// channel failed.
+///
+/// This bound was formerly absent and such failures panicked. A sink whose error
+/// does not convert from a `TransportError` should be mapped with
+/// `sink_map_err` to an error that does, such as `anyhow::Error` or an error
+/// wrapping its own.
 #[kind]
 impl<T, E> Kind for Sink<T, E>
 where
     T: Kind,
-    E: Kind,
+    E: Kind + From<TransportError>,
 {
-    type ConstructItem = ForkHandle;
+    type ConstructItem = Feedback;
     type ConstructError = Void;
     type ConstructFuture = Future<ConstructResult<Self>>;
     type DeconstructItem = ForkHandle;
-    type DeconstructError = WrappedError<Void>;
+    type DeconstructError = WrappedError<E::DeconstructError>;
     type DeconstructFuture = Future<DeconstructResult<Self>>;
     fn deconstruct<C: Channel<Self::DeconstructItem, Self::ConstructItem>>(
         mut self,
         mut channel: C,
     ) -> Self::DeconstructFuture {
         Box::pin(async move {
+            channel
+                .send(Feedback::Credit(CREDIT_WINDOW))
+                .await
+                .map_err(WrappedError::Send)?;
+            let mut consumed = 0;
             while let Some(handle) = channel.next().await {
-                if let Err(error) = self
-                    .send(channel.get_fork::<T>(handle).await.unwrap())
-                    .await
-                {
-                    let handle = channel.fork::<E>(error).await.unwrap();
-                    channel.send(handle).await.map_err(WrappedError::Send)?;
+                // an item that cannot be constructed is reported to the writer in the
+                // same way as a failure of the underlying sink
+                let result = match channel.get_fork::<T>(handle).await {
+                    Ok(item) => self.send(item).await,
+                    Err(e) => Err(E::from(TransportError::new(e.into()))),
+                };
+                if let Err(error) = result {
+                    let handle = channel.fork::<E>(error).await?;
+                    channel
+                        .send(Feedback::Error(handle))
+                        .await
+                        .map_err(WrappedError::Send)?;
+                }
+                consumed += 1;
+                if consumed >= CREDIT_WINDOW / 2 {
+                    channel
+                        .send(Feedback::Credit(consumed))
+                        .await
+                        .map_err(WrappedError::Send)?;
+                    consumed = 0;
                 }
             }
             Ok(())
@@ -97,9 +208,15 @@ where
     ) -> Self::ConstructFuture {
         Box::pin(async move {
             Ok(Box::pin(KindSink {
-                channel: Arc::new(Mutex::new(channel)),
+                state: Arc::new(Mutex::new(SinkState {
+                    channel,
+                    credits: 0,
+                    closed: false,
+                    failure: None,
+                    error: None,
+                })),
                 _marker: PhantomData,
-                item: Box::pin(ready(())),
+                item: Box::pin(ready(Ok(()))),
             }) as Sink<T, E>)
         })
     }
@@ -5,9 +5,21 @@ use crate::{
     ConstructResult, DeconstructResult, Kind,
 };
 
-use futures::{stream::unfold, SinkExt, StreamExt};
+use futures::{
+    future::{select, Either},
+    stream::unfold,
+    SinkExt, StreamExt,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{Cancel, WrappedError, CREDIT_WINDOW};
 
-use super::WrappedError;
+/// Sent from the constructed stream to its source.
+#[derive(Serialize, Deserialize)]
+pub enum Demand {
+    Credit(u32),
+    Cancel,
+}
 
 #[kind]
 impl<T> Kind for Stream<T>
@@ -17,7 +29,7 @@ where
     type ConstructItem = Option<ForkHandle>;
     type ConstructError = WrappedError<T::ConstructError>;
     type ConstructFuture = Future<ConstructResult<Self>>;
-    type DeconstructItem = ();
+    type DeconstructItem = Demand;
     type DeconstructError = WrappedError<T::DeconstructError>;
     type DeconstructFuture = Future<DeconstructResult<Self>>;
     fn deconstruct<C: Channel<Self::DeconstructItem, Self::ConstructItem>>(
@@ -25,29 +37,68 @@ where
         mut channel: C,
     ) -> Self::DeconstructFuture {
         Box::pin(async move {
-            while let Some(item) = self.next().await {
-                channel
-                    .send(Some(channel.fork(item).await?))
-                    .await
-                    .map_err(WrappedError::Send)?
+            let mut credits = 0;
+            loop {
+                let event = if credits == 0 {
+                    Either::Right(channel.next().await)
+                } else {
+                    match select(self.next(), channel.next()).await {
+                        Either::Left((item, _)) => Either::Left(item),
+                        Either::Right((demand, _)) => Either::Right(demand),
+                    }
+                };
+                match event {
+                    Either::Left(Some(item)) => {
+                        channel
+                            .send(Some(channel.fork(item).await?))
+                            .await
+                            .map_err(WrappedError::Send)?;
+                        credits -= 1;
+                    }
+                    Either::Left(None) => break,
+                    Either::Right(Some(Demand::Credit(granted))) => credits += granted,
+                    Either::Right(Some(Demand::Cancel)) | Either::Right(None) => return Ok(()),
+                }
             }
             channel.send(None).await.map_err(WrappedError::Send)?;
             Ok(())
         })
     }
     fn construct<C: Channel<Self::ConstructItem, Self::DeconstructItem>>(
-        channel: C,
+        mut channel: C,
     ) -> Self::ConstructFuture {
         Box::pin(async move {
-            Ok(Box::pin(unfold(channel, |mut channel| {
-                async move {
-                    if let Some(handle) = channel.next().await.unwrap() {
-                        Some((channel.get_fork(handle).await.unwrap(), channel))
-                    } else {
-                        None
+            channel
+                .send(Demand::Credit(CREDIT_WINDOW))
+                .await
+                .map_err(WrappedError::Send)?;
+            Ok(Box::pin(unfold(
+                (Cancel::new(channel, Demand::Cancel), 0),
+                |(mut channel, mut consumed)| async move {
+                    // the stream ends early if the channel closes or an item cannot be
+                    // constructed, as a `Stream<T>` has no item with which to report
+                    // the failure; items of a `Stream<Result<T, E>>` carry failures of
+                    // the remote stream itself
+                    let handle = match channel.channel().next().await? {
+                        Some(handle) => handle,
+                        None => {
+                            channel.disarm();
+                            return None;
+                        }
+                    };
+                    let item = channel.channel().get_fork(handle).await.ok()?;
+                    consumed += 1;
+                    if consumed >= CREDIT_WINDOW / 2 {
+                        channel
+                            .channel()
+                            .send(Demand::Credit(consumed))
+                            .await
+                            .ok()?;
+                        consumed = 0;
                     }
-                }
-            })) as Stream<T>)
+                    Some((item, (channel, consumed)))
+                },
+            )) as Stream<T>)
         })
     }
 }
@@ -1,4 +1,9 @@
-use crate::{channel::Channel, kind, kind::Future, ConstructResult, DeconstructResult, Kind};
+use crate::{
+    channel::Channel,
+    kind,
+    kind::{Future, Packing},
+    ConstructResult, DeconstructResult, Kind,
+};
 
 use futures::{SinkExt, StreamExt};
 use url::{ParseError, Url};
@@ -40,4 +45,10 @@ impl Kind for Url {
                 .parse()?)
         })
     }
+    fn packing() -> Option<Packing<Self>> {
+        Some(Packing {
+            pack: Url::into_string,
+            unpack: |item| Ok(item.parse()?),
+        })
+    }
 }
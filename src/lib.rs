@@ -14,9 +14,11 @@ pub mod format;
 pub use format::{ApplyDecode, ApplyEncode};
 pub mod core;
 pub mod kind;
-use kind::{ConstructResult, DeconstructResult};
+use kind::{ConstructResult, DeconstructResult, Packing};
 pub mod reflect;
 pub mod replicate;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod testing;
 
 use ::core::any::Any;
 use downcast_rs::{impl_downcast, Downcast};
@@ -37,7 +39,7 @@ use std::error::Error;
 ///
 /// #[object]
 /// pub trait Object<T: Kind> {
-///     fn test(&self) -> Future<T>;
+///     fn test(&self) -> Infallible<T>;
 /// }
 /// ```
 /// The above will generate an implementation of Kind for `Box<dyn Object<T>>` where `T: Kind`.
@@ -187,6 +189,15 @@ pub trait Kind: Any + Sized + Sync + Send + Unpin + 'static {
         channel: C,
     ) -> Self::DeconstructFuture;
 
+    /// Describes how to convert this `Kind` to and from a single `ConstructItem`
+    /// without a channel. Only `Kind`s whose entire communication is exactly one
+    /// `ConstructItem`, with no forks or replies, should return `Some`; collections
+    /// of such `Kind`s are then transmitted as a single packed item rather than
+    /// one fork per element.
+    fn packing() -> Option<Packing<Self>> {
+        None
+    }
+
     #[doc(hidden)]
     const USE_KIND_MACRO_TO_GENERATE_THIS_FIELD: [u8; 32];
 }
@@ -209,14 +220,29 @@ impl<T: ?Sized> SerdeAny for T where T: ErasedSerialize + Downcast + Sync + Send
 /// Logs information to a target-appropriate console.
 ///
 /// `log!` uses the same syntax as `format!`, `println!`, etc. and delegates to `format!` under the hood.
+/// A level and key/value fields may precede the format arguments, each followed by a semicolon.
 /// ```
 /// use vessels::log;
 ///
 /// log!("the answer is {}", 12);
+/// log!(Warn; "the answer is {}", 12);
+/// log!(Debug; question = "unknown", answer = 42; "the answer is {}", 42);
 /// ```
-/// Currently not supported inside individual vessels.
+/// Inside a vessel records are sent to the `Log` capability of its handle, see `core::log`.
 #[macro_export]
 macro_rules! log {
+    ($level:ident; $($key:ident = $value:expr),+; $($args:expr),+ $(,)?) => (
+        let formatted = format!($($args,)*);
+        $crate::core::LOG.log(
+            $crate::core::log::Level::$level,
+            formatted,
+            vec![$((stringify!($key).to_owned(), $value.to_string())),+],
+        )
+    );
+    ($level:ident; $($args:expr),+ $(,)?) => (
+        let formatted = format!($($args,)*);
+        $crate::core::LOG.log($crate::core::log::Level::$level, formatted, vec![])
+    );
     ($($args:expr),*) => (
         let formatted = format!($($args,)*);
         $crate::core::LOG.info(formatted)
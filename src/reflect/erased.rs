@@ -0,0 +1,478 @@
+use super::{
+    CallError, Cast, CastError, Erased, MethodIndex, MethodTypes, NameError, OutOfRangeError,
+    Reflected, SomeTrait, Trait,
+};
+
+use crate::{
+    channel::{Channel, ForkHandle, IdChannel},
+    format::{ApplyDecode, ApplyEncode, Cbor},
+    kind,
+    kind::{
+        ConstructResult, DeconstructResult, Fallible, Future, SinkStream, TransportError,
+        WrappedError,
+    },
+    Kind, OnTo,
+};
+
+use alloc::sync::Arc;
+use anyhow::{anyhow, Error};
+use core::{
+    any::{Any, TypeId},
+    mem::replace,
+};
+use futures::{lock, SinkExt, StreamExt};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard, RwLock},
+};
+use thiserror::Error;
+use void::Void;
+
+/// A reflected trait object encoded for transfer independently of the channel it is sent over.
+pub type Transport = Fallible<SinkStream<Vec<u8>, Error, Vec<u8>>, Error>;
+
+pub fn encode_transport<K: Kind>(item: K) -> Transport {
+    Box::pin(async move {
+        let (sink, stream) = item.on_to::<IdChannel>().await.encode::<Cbor>().split();
+        Ok(SinkStream::new(sink.sink_map_err(Error::from), stream))
+    })
+}
+
+pub fn decode_transport<K: Kind>(transport: Transport) -> Fallible<K, Error> {
+    Box::pin(async move {
+        Ok(transport
+            .await?
+            .sink_map_err(TransportError::new)
+            .decode::<IdChannel, Cbor>()
+            .await?)
+    })
+}
+
+type FromTransport = fn(Transport) -> Result<Box<dyn Erased>, CastError>;
+
+pub(crate) struct Registry {
+    items: RwLock<HashMap<TypeId, ([u8; 32], FromTransport)>>,
+}
+
+impl Registry {
+    pub(crate) fn add<T: ?Sized + Reflected>(&self) {
+        if !self.items.read().unwrap().contains_key(&TypeId::of::<T>()) {
+            self.items
+                .write()
+                .unwrap()
+                .insert(TypeId::of::<T>(), (T::IDENTITY, T::from_transport));
+        }
+    }
+
+    fn get(&self, ty: TypeId) -> Option<([u8; 32], FromTransport)> {
+        self.items.read().unwrap().get(&ty).copied()
+    }
+
+    fn find(&self, identity: &[u8; 32]) -> Option<TypeId> {
+        self.items
+            .read()
+            .unwrap()
+            .iter()
+            .find(|(_, (item, _))| item == identity)
+            .map(|(ty, _)| *ty)
+    }
+}
+
+/// Makes the trait `T` known locally, so that remote erased objects of it can be
+/// called through reflection before any of them has been cast.
+pub fn register<T: ?Sized + Reflected>() {
+    REGISTRY.add::<T>();
+}
+
+lazy_static! {
+    pub(crate) static ref REGISTRY: Registry = Registry {
+        items: RwLock::new(HashMap::new()),
+    };
+}
+
+#[derive(Debug, Error)]
+pub enum DeferredError {
+    #[error("deferred object failed to resolve: {0}")]
+    Failed(String),
+    #[error("deferred object is in use by an outstanding call")]
+    Borrowed,
+    #[error("deferred object was moved out by an owned receiver")]
+    Taken,
+}
+
+enum DeferredState<T: ?Sized> {
+    Pending(Fallible<Box<T>, Error>),
+    Ready(Arc<Mutex<Box<T>>>),
+    Failed(String),
+    Taken,
+}
+
+/// A trait object that is not yet available.
+///
+/// Casting a remote erased object must return synchronously, so the resulting trait object
+/// is backed by a `Deferred` that resolves the remote object when it is first called.
+pub struct Deferred<T: ?Sized> {
+    state: lock::Mutex<DeferredState<T>>,
+}
+
+impl<T: ?Sized> Deferred<T> {
+    pub fn new(object: Fallible<Box<T>, Error>) -> Arc<Self> {
+        Arc::new(Deferred {
+            state: lock::Mutex::new(DeferredState::Pending(object)),
+        })
+    }
+
+    pub async fn get(&self) -> Result<Arc<Mutex<Box<T>>>, DeferredError> {
+        let mut state = self.state.lock().await;
+        if let DeferredState::Pending(object) = &mut *state {
+            let object = object.await;
+            *state = match object {
+                Ok(object) => DeferredState::Ready(Arc::new(Mutex::new(object))),
+                Err(e) => DeferredState::Failed(format!("{}", e)),
+            };
+        }
+        match &*state {
+            DeferredState::Ready(object) => Ok(object.clone()),
+            DeferredState::Failed(cause) => Err(DeferredError::Failed(cause.clone())),
+            _ => Err(DeferredError::Taken),
+        }
+    }
+
+    pub async fn take(&self) -> Result<Box<T>, DeferredError> {
+        let mut state = self.state.lock().await;
+        match replace(&mut *state, DeferredState::Taken) {
+            DeferredState::Pending(object) => object
+                .await
+                .map_err(|e| DeferredError::Failed(format!("{}", e))),
+            DeferredState::Ready(object) => match Arc::try_unwrap(object) {
+                Ok(object) => Ok(object.into_inner().unwrap()),
+                Err(object) => {
+                    *state = DeferredState::Ready(object);
+                    Err(DeferredError::Borrowed)
+                }
+            },
+            DeferredState::Failed(cause) => {
+                *state = DeferredState::Failed(cause.clone());
+                Err(DeferredError::Failed(cause))
+            }
+            DeferredState::Taken => Err(DeferredError::Taken),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Descriptor {
+    name: String,
+    methods: Vec<String>,
+    targets: Vec<[u8; 32]>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum ErasedItem {
+    Descriptor(Descriptor),
+    Transport(Option<ForkHandle>),
+}
+
+type CastTransport = fn(Box<dyn Erased>) -> Result<Transport, CastError>;
+
+fn downcast_transport<T: ?Sized + Reflected>(
+    object: Box<dyn Erased>,
+) -> Result<Transport, CastError> {
+    Cast::<T>::downcast(object).map(T::into_transport)
+}
+
+fn upcast_transport<T: ?Sized + Reflected>(
+    object: Box<dyn Erased>,
+) -> Result<Transport, CastError> {
+    Cast::<T>::upcast(object).map(T::into_transport)
+}
+
+enum Source {
+    Local(Box<dyn Erased>, Vec<CastTransport>),
+    Remote(Box<dyn FnOnce([u8; 32]) -> Transport + Sync + Send>),
+}
+
+/// The traits an erased object can be cast to on the far side of a channel
+/// and the means of producing each of them.
+pub struct Transfer {
+    descriptor: Descriptor,
+    source: Source,
+}
+
+impl Transfer {
+    pub fn new<T: ?Sized + Reflected + Trait<T>>(object: Box<T>) -> Self {
+        let methods = (0..Trait::<T>::count(&*object))
+            .filter_map(|index| Trait::<T>::name_of(&*object, index).ok())
+            .collect();
+        Transfer {
+            descriptor: Descriptor {
+                name: Trait::<T>::name(&*object),
+                methods,
+                targets: vec![T::IDENTITY],
+            },
+            source: Source::Local(
+                Trait::<T>::erase(object),
+                vec![downcast_transport::<T> as CastTransport],
+            ),
+        }
+    }
+
+    pub fn with_supertrait<S: ?Sized + Reflected>(mut self) -> Self {
+        if let Source::Local(_, casts) = &mut self.source {
+            self.descriptor.targets.push(S::IDENTITY);
+            casts.push(upcast_transport::<S>);
+        }
+        self
+    }
+
+    fn select(self, target: [u8; 32]) -> Option<Transport> {
+        let index = self
+            .descriptor
+            .targets
+            .iter()
+            .position(|item| item == &target)?;
+        match self.source {
+            Source::Local(object, casts) => casts[index](object).ok(),
+            Source::Remote(request) => Some(request(target)),
+        }
+    }
+}
+
+fn request<C: Channel<ErasedItem, [u8; 32]>>(mut channel: C, target: [u8; 32]) -> Transport {
+    Box::pin(async move {
+        channel.send(target).await?;
+        match channel.next().await {
+            Some(ErasedItem::Transport(Some(handle))) => Ok(channel
+                .get_fork::<SinkStream<Vec<u8>, Error, Vec<u8>>>(handle)
+                .await?),
+            _ => Err(anyhow!("erased object rejected cast")),
+        }
+    })
+}
+
+enum ProxyState<C> {
+    Unresolved(C),
+    Resolved(Box<dyn Erased>),
+    Spent,
+}
+
+struct Proxy<C: Channel<ErasedItem, [u8; 32]>> {
+    descriptor: Descriptor,
+    state: Mutex<ProxyState<C>>,
+}
+
+impl<C: Channel<ErasedItem, [u8; 32]>> Proxy<C> {
+    fn reconstruct(self, ty: TypeId, upcast: bool) -> Result<Box<dyn Erased>, CastError> {
+        let channel = match self.state.into_inner().unwrap() {
+            ProxyState::Unresolved(channel) => channel,
+            ProxyState::Resolved(object) => {
+                return if upcast {
+                    object.upcast_erased(ty)
+                } else {
+                    Ok(object)
+                };
+            }
+            ProxyState::Spent => return Err(CastError { target: ty }),
+        };
+        let (identity, from_transport) = REGISTRY.get(ty).ok_or(CastError { target: ty })?;
+        let mut targets = self.descriptor.targets.iter();
+        let offered = if upcast {
+            targets.skip(1).any(|item| item == &identity)
+        } else {
+            targets.next() == Some(&identity)
+        };
+        if offered {
+            from_transport(request(channel, identity))
+        } else {
+            Err(CastError { target: ty })
+        }
+    }
+
+    /// Reconstructs the object as its own trait so that it can be called through
+    /// reflection. The trait must be known locally, which is the case once any
+    /// object of it has been cast or it has been passed to
+    /// [`register`](fn.register.html). No request is made of the remote end until
+    /// the result of a call is first polled.
+    fn resolve(&self) -> Result<MutexGuard<'_, ProxyState<C>>, CallError> {
+        let mut state = self.state.lock().unwrap();
+        if let ProxyState::Unresolved(_) = &*state {
+            let identity = *self.descriptor.targets.first().ok_or(CallError::Remote)?;
+            let ty = REGISTRY.find(&identity).ok_or(CallError::Remote)?;
+            let (_, from_transport) = REGISTRY.get(ty).ok_or(CallError::Remote)?;
+            let channel = match replace(&mut *state, ProxyState::Spent) {
+                ProxyState::Unresolved(channel) => channel,
+                _ => unreachable!(),
+            };
+            *state = ProxyState::Resolved(
+                from_transport(request(channel, identity)).map_err(|_| CallError::Remote)?,
+            );
+        }
+        match &*state {
+            ProxyState::Resolved(_) => Ok(state),
+            _ => Err(CallError::Remote),
+        }
+    }
+}
+
+impl<C: Channel<ErasedItem, [u8; 32]>> Trait<SomeTrait> for Proxy<C> {
+    fn call(
+        &self,
+        index: MethodIndex,
+        args: Vec<Box<dyn Any + Send + Sync>>,
+    ) -> Result<Box<dyn Any + Send + Sync>, CallError> {
+        match &*self.resolve()? {
+            ProxyState::Resolved(object) => object.call(index, args),
+            _ => Err(CallError::Remote),
+        }
+    }
+    fn call_mut(
+        &mut self,
+        index: MethodIndex,
+        args: Vec<Box<dyn Any + Send + Sync>>,
+    ) -> Result<Box<dyn Any + Send + Sync>, CallError> {
+        match &mut *self.resolve()? {
+            ProxyState::Resolved(object) => object.call_mut(index, args),
+            _ => Err(CallError::Remote),
+        }
+    }
+    fn call_move(
+        self: Box<Self>,
+        index: MethodIndex,
+        args: Vec<Box<dyn Any + Send + Sync>>,
+    ) -> Result<Box<dyn Any + Send + Sync>, CallError> {
+        drop(self.resolve()?);
+        match self.state.into_inner().unwrap() {
+            ProxyState::Resolved(object) => object.call_move(index, args),
+            _ => Err(CallError::Remote),
+        }
+    }
+    fn by_name(&self, name: &'_ str) -> Result<MethodIndex, NameError> {
+        self.descriptor
+            .methods
+            .iter()
+            .position(|method| method == name)
+            .map(|index| index as MethodIndex)
+            .ok_or_else(|| NameError {
+                name: name.to_owned(),
+            })
+    }
+    fn count(&self) -> MethodIndex {
+        self.descriptor.methods.len() as MethodIndex
+    }
+    fn name_of(&self, index: MethodIndex) -> Result<String, OutOfRangeError> {
+        self.descriptor
+            .methods
+            .get(index as usize)
+            .cloned()
+            .ok_or(OutOfRangeError { index })
+    }
+    fn this(&self) -> TypeId {
+        self.descriptor
+            .targets
+            .first()
+            .and_then(|identity| REGISTRY.find(identity))
+            .unwrap_or_else(TypeId::of::<SomeTrait>)
+    }
+    fn name(&self) -> String {
+        self.descriptor.name.clone()
+    }
+    /// Type information is local to a process and is not transmitted, so it is
+    /// available only if the object's trait is known locally, as for `call`.
+    fn types(&self, index: MethodIndex) -> Result<MethodTypes, OutOfRangeError> {
+        match self.resolve().as_deref() {
+            Ok(ProxyState::Resolved(object)) => object.types(index),
+            _ => Err(OutOfRangeError { index }),
+        }
+    }
+    fn supertraits(&self) -> Vec<TypeId> {
+        self.descriptor
+            .targets
+            .iter()
+            .skip(1)
+            .filter_map(|identity| REGISTRY.find(identity))
+            .collect()
+    }
+    fn upcast_erased(self: Box<Self>, ty: TypeId) -> Result<Box<dyn Erased>, CastError> {
+        self.reconstruct(ty, true)
+    }
+    fn erase(self: Box<Self>) -> Box<dyn Erased> {
+        self
+    }
+}
+
+impl<C: Channel<ErasedItem, [u8; 32]>> Erased for Proxy<C> {
+    fn cast(self: Box<Self>, ty: TypeId) -> Result<Box<dyn Any + Send + Sync>, CastError> {
+        self.reconstruct(ty, false)?.cast(ty)
+    }
+    fn transfer(self: Box<Self>) -> Transfer {
+        let Proxy { descriptor, state } = *self;
+        match state.into_inner().unwrap() {
+            ProxyState::Unresolved(channel) => Transfer {
+                descriptor,
+                source: Source::Remote(Box::new(move |target| request(channel, target))),
+            },
+            ProxyState::Resolved(object) => object.transfer(),
+            ProxyState::Spent => Transfer {
+                descriptor,
+                source: Source::Remote(Box::new(|_| {
+                    Box::pin(async { Err(anyhow!("erased object was moved out")) })
+                })),
+            },
+        }
+    }
+}
+
+#[kind]
+impl Kind for Box<dyn Erased> {
+    type ConstructItem = ErasedItem;
+    type ConstructError = WrappedError<Void>;
+    type ConstructFuture = Future<ConstructResult<Self>>;
+    type DeconstructItem = [u8; 32];
+    type DeconstructError = WrappedError<Void>;
+    type DeconstructFuture = Future<DeconstructResult<Self>>;
+    fn deconstruct<C: Channel<Self::DeconstructItem, Self::ConstructItem>>(
+        self,
+        mut channel: C,
+    ) -> Self::DeconstructFuture {
+        Box::pin(async move {
+            let transfer = self.transfer();
+            channel
+                .send(ErasedItem::Descriptor(transfer.descriptor.clone()))
+                .await
+                .map_err(WrappedError::Send)?;
+            if let Some(target) = channel.next().await {
+                let handle = match transfer.select(target) {
+                    Some(transport) => match transport.await {
+                        Ok(transport) => Some(channel.fork(transport).await?),
+                        Err(_) => None,
+                    },
+                    None => None,
+                };
+                channel
+                    .send(ErasedItem::Transport(handle))
+                    .await
+                    .map_err(WrappedError::Send)?;
+            }
+            Ok(())
+        })
+    }
+    fn construct<C: Channel<Self::ConstructItem, Self::DeconstructItem>>(
+        mut channel: C,
+    ) -> Self::ConstructFuture {
+        Box::pin(async move {
+            match channel.next().await {
+                Some(ErasedItem::Descriptor(descriptor)) => Ok(Box::new(Proxy {
+                    descriptor,
+                    state: Mutex::new(ProxyState::Unresolved(channel)),
+                })
+                    as Box<dyn Erased>),
+                _ => Err(WrappedError::Insufficient {
+                    got: 0,
+                    expected: 1,
+                }),
+            }
+        })
+    }
+}
@@ -1,3 +1,9 @@
+mod erased;
+use erased::REGISTRY;
+#[doc(hidden)]
+pub use erased::{decode_transport, encode_transport, Descriptor, ErasedItem, Transport};
+pub use erased::{register, Deferred, DeferredError, Transfer};
+
 use crate::Kind;
 use core::{
     any::{Any, TypeId},
@@ -23,6 +29,8 @@ pub enum CallError {
     OutOfRange(#[source] OutOfRangeError),
     #[error("expected {0} receiver")]
     IncorrectReceiver(Receiver),
+    #[error("the trait of a remote erased object must be known locally to call its methods")]
+    Remote,
 }
 
 #[derive(Debug, Error)]
@@ -63,6 +71,12 @@ pub trait Reflected: 'static {
     type ErasedShim: From<Box<Self>>;
     #[doc(hidden)]
     const DO_NOT_IMPLEMENT_THIS_MARKER_TRAIT_MANUALLY: ();
+    #[doc(hidden)]
+    const IDENTITY: [u8; 32];
+    #[doc(hidden)]
+    fn into_transport(self: Box<Self>) -> Transport;
+    #[doc(hidden)]
+    fn from_transport(transport: Transport) -> Result<Box<dyn Erased>, CastError>;
 }
 
 #[derive(Debug)]
@@ -105,14 +119,31 @@ impl Reflected for SomeTrait {
     type Shim = ();
     type ErasedShim = ();
     const DO_NOT_IMPLEMENT_THIS_MARKER_TRAIT_MANUALLY: () = ();
+    const IDENTITY: [u8; 32] = [0u8; 32];
+    fn into_transport(self: Box<Self>) -> Transport {
+        match *self {}
+    }
+    fn from_transport(_: Transport) -> Result<Box<dyn Erased>, CastError> {
+        Err(CastError {
+            target: TypeId::of::<SomeTrait>(),
+        })
+    }
 }
 
 impl From<Box<SomeTrait>> for () {
     fn from(_: Box<SomeTrait>) {}
 }
 
+/// A type-erased reflected trait object.
+///
+/// `Box<dyn Erased>` is itself a `Kind`. An erased object received over a channel can be cast
+/// to its own trait or upcast to any of that trait's supertraits exactly as a local one, provided the
+/// target trait is annotated with `#[object]` in the receiving crate. Reflected method calls through
+/// `Trait::call` are only available on local erased objects, as their arguments are not serializable.
 pub trait Erased: Send + Trait<SomeTrait> {
     fn cast(self: Box<Self>, ty: TypeId) -> Result<Box<dyn Any + Send + Sync>, CastError>;
+    #[doc(hidden)]
+    fn transfer(self: Box<Self>) -> Transfer;
 }
 
 pub trait Cast<T: ?Sized + Reflected> {
@@ -122,6 +153,7 @@ pub trait Cast<T: ?Sized + Reflected> {
 
 impl<S: ?Sized + Reflected> Cast<S> for Box<dyn Erased> {
     fn downcast(self) -> Result<Box<S>, CastError> {
+        REGISTRY.add::<S>();
         self.cast(TypeId::of::<S>()).map(|erased| {
             *Box::<dyn Any>::downcast::<Box<S>>(erased)
                 .map_err(|_| panic!("could not downcast after successful reinterpretation"))
@@ -129,6 +161,7 @@ impl<S: ?Sized + Reflected> Cast<S> for Box<dyn Erased> {
         })
     }
     fn upcast(self) -> Result<Box<S>, CastError> {
+        REGISTRY.add::<S>();
         Trait::<SomeTrait>::upcast_erased(self, TypeId::of::<S>()).map(|erased| {
             erased
                 .downcast()
@@ -0,0 +1,234 @@
+//! Helpers for verifying that a `Kind` survives transport.
+//!
+//! [`round_trip`](fn.round_trip.html) deconstructs a value onto an `IdChannel`,
+//! encodes it with the chosen `Format`, and decodes and reconstructs it on a
+//! second `IdChannel`, as two peers would.
+//! [`round_trip_with`](fn.round_trip_with.html) does the same under adverse
+//! [`Conditions`](struct.Conditions.html).
+//! ```
+//! use futures::executor::block_on;
+//! use vessels::{format::Cbor, testing::{round_trip_with, Conditions}};
+//!
+//! block_on(async {
+//!     let value: Vec<String> = round_trip_with::<Cbor, _>(
+//!         vec!["hello".to_owned()],
+//!         Conditions { reorder: 8, ..Conditions::default() },
+//!     )
+//!     .await
+//!     .unwrap();
+//!     assert_eq!(value, vec!["hello".to_owned()]);
+//! });
+//! ```
+
+use crate::{
+    channel::{
+        id_channel::{IdChannelError, Item, CONTROL},
+        Context, IdChannel,
+    },
+    core::spawn,
+    format::{ApplyDecode, Encode, Format},
+    kind::{ConstructResult, Fallible, SinkStream, Stream},
+    Kind, OnTo,
+};
+
+use core::pin::Pin;
+use futures::{
+    channel::mpsc::unbounded,
+    future::ready,
+    task::{Context as FContext, Poll},
+    FutureExt, Sink as ISink, Stream as IStream, StreamExt,
+};
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::{mpsc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The conditions under which a round trip takes place.
+#[derive(Clone, Debug)]
+pub struct Conditions {
+    /// The number of items sent by the deconstructing side that may be held back
+    /// and released out of order. Items of the same fork are never reordered
+    /// with respect to each other, as the transport guarantees their order.
+    pub reorder: usize,
+    /// The delay applied to every frame in either direction.
+    pub latency: Duration,
+    /// The seed from which the reordering is derived, so that a failing round
+    /// trip can be reproduced.
+    pub seed: u64,
+}
+
+impl Default for Conditions {
+    fn default() -> Self {
+        Conditions {
+            reorder: 0,
+            latency: Duration::from_secs(0),
+            seed: 1,
+        }
+    }
+}
+
+/// Deconstructs `kind` and reconstructs it through the `Format` `F`, returning the
+/// reconstructed value.
+pub fn round_trip<F: Format + 'static, K: Kind>(kind: K) -> Fallible<K, K::ConstructError>
+where
+    F::Representation: Clone + Sync + Send + 'static,
+{
+    round_trip_with::<F, K>(kind, Conditions::default())
+}
+
+/// Deconstructs `kind` and reconstructs it through the `Format` `F` under the
+/// provided `Conditions`, returning the reconstructed value.
+pub fn round_trip_with<F: Format + 'static, K: Kind>(
+    kind: K,
+    conditions: Conditions,
+) -> Fallible<K, K::ConstructError>
+where
+    F::Representation: Clone + Sync + Send + 'static,
+{
+    Box::pin(async move {
+        let channel = Reorder::new(
+            kind.on_to::<IdChannel>().await,
+            conditions.reorder,
+            conditions.seed,
+        );
+        let (sink, stream) = <F as Encode<_>>::encode(channel).split();
+        let (sender, receiver) = unbounded();
+        spawn(
+            delay(receiver, conditions.latency)
+                .map(Ok)
+                .forward(sink)
+                .map(|_| ()),
+        );
+        let kind: ConstructResult<K> = SinkStream::new(sender, delay(stream, conditions.latency))
+            .decode::<IdChannel, F>()
+            .await;
+        kind
+    })
+}
+
+/// Delays every item of `stream` by `latency`, preserving their order.
+fn delay<T: Sync + Send + 'static>(
+    stream: impl IStream<Item = T> + Sync + Send + 'static,
+    latency: Duration,
+) -> Stream<T> {
+    if latency == Duration::from_secs(0) {
+        return Box::pin(stream);
+    }
+    let (sender, receiver) = mpsc::channel::<(Instant, T)>();
+    let (output, delayed) = unbounded();
+    thread::spawn(move || {
+        for (due, item) in receiver {
+            let now = Instant::now();
+            if due > now {
+                thread::sleep(due - now);
+            }
+            if output.unbounded_send(item).is_err() {
+                break;
+            }
+        }
+    });
+    let sender = Mutex::new(sender);
+    spawn(stream.for_each(move |item| {
+        let _ = sender
+            .lock()
+            .unwrap()
+            .send((Instant::now() + latency, item));
+        ready(())
+    }));
+    Box::pin(delayed)
+}
+
+/// Wraps an `IdChannel`, releasing the items it sends in a pseudorandom order that
+/// preserves the order of items within each fork. Control items are never
+/// reordered, as the reclamation of fork handles depends on their position.
+struct Reorder {
+    channel: IdChannel,
+    buffer: VecDeque<Item>,
+    window: usize,
+    state: u64,
+    done: bool,
+}
+
+impl Reorder {
+    fn new(channel: IdChannel, window: usize, seed: u64) -> Self {
+        Reorder {
+            channel,
+            buffer: VecDeque::new(),
+            window: window.max(1),
+            state: seed.max(1),
+            done: false,
+        }
+    }
+
+    fn next_random(&mut self) -> usize {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state as usize
+    }
+}
+
+impl IStream for Reorder {
+    type Item = Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut FContext) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        while !this.done && this.buffer.len() < this.window {
+            match this.channel.poll_next_unpin(cx) {
+                Poll::Ready(Some(item)) => this.buffer.push_back(item),
+                Poll::Ready(None) => this.done = true,
+                Poll::Pending => break,
+            }
+        }
+        if this.buffer.is_empty() {
+            return if this.done {
+                Poll::Ready(None)
+            } else {
+                Poll::Pending
+            };
+        }
+        let mut seen = HashSet::new();
+        let mut eligible = vec![];
+        for (index, item) in this.buffer.iter().enumerate() {
+            if item.0 == CONTROL {
+                if index == 0 {
+                    eligible.push(index);
+                }
+                break;
+            }
+            if seen.insert(item.0) {
+                eligible.push(index);
+            }
+        }
+        let index = eligible[this.next_random() % eligible.len()];
+        Poll::Ready(this.buffer.remove(index))
+    }
+}
+
+impl ISink<Item> for Reorder {
+    type Error = IdChannelError;
+
+    fn start_send(mut self: Pin<&mut Self>, item: Item) -> Result<(), Self::Error> {
+        Pin::new(&mut self.channel).start_send(item)
+    }
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut FContext) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.channel).poll_ready(cx)
+    }
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut FContext) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.channel).poll_flush(cx)
+    }
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut FContext) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.channel).poll_close(cx)
+    }
+}
+
+impl<'de> Context<'de> for Reorder {
+    type Item = Item;
+    type Target = <IdChannel as Context<'de>>::Target;
+
+    fn context(&self) -> Self::Target {
+        self.channel.context()
+    }
+}
@@ -0,0 +1,36 @@
+#![cfg(feature = "bytes")]
+
+use bytes::Bytes;
+use futures::executor::block_on;
+use vessels::{
+    format::{Cbor, Format, Json},
+    testing::round_trip,
+    Kind,
+};
+
+#[test]
+fn bytes_are_packable() {
+    assert!(Bytes::packing().is_some());
+}
+
+#[test]
+fn bytes_round_trip() {
+    block_on(async {
+        let blob = Bytes::from((0..=255u8).cycle().take(4096).collect::<Vec<_>>());
+        assert_eq!(round_trip::<Cbor, _>(blob.clone()).await.unwrap(), blob);
+        assert_eq!(round_trip::<Json, _>(blob.clone()).await.unwrap(), blob);
+        assert_eq!(
+            round_trip::<Cbor, _>(Bytes::new()).await.unwrap(),
+            Bytes::new()
+        );
+    });
+}
+
+#[test]
+fn bytes_are_encoded_as_a_byte_string() {
+    let blob = vec![0xff; 1024];
+    // as an array each byte would take two, as a byte string only the header is
+    // added
+    assert!(Cbor::serialize(Bytes::from(blob.clone())).len() < blob.len() + 8);
+    assert!(Cbor::serialize(blob.clone()).len() > blob.len() * 2);
+}
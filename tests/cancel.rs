@@ -0,0 +1,46 @@
+use futures::{
+    channel::oneshot::{channel, Sender},
+    executor::block_on,
+    future::pending,
+    stream,
+};
+use vessels::{
+    format::Cbor,
+    kind::{Infallible, Stream},
+    testing::round_trip,
+};
+
+// signals its receiver when dropped along with the remote work holding it
+fn guard() -> (Sender<()>, impl std::future::Future<Output = ()>) {
+    let (sender, receiver) = channel::<()>();
+    (sender, async move {
+        assert!(receiver.await.is_err());
+    })
+}
+
+#[test]
+fn dropping_future_cancels_remote() {
+    block_on(async {
+        let (guard, dropped) = guard();
+        let future: Infallible<u32> = Box::pin(async move {
+            let _guard = guard;
+            pending::<()>().await;
+            Ok(0)
+        });
+        drop(round_trip::<Cbor, _>(future).await.unwrap());
+        dropped.await;
+    });
+}
+
+#[test]
+fn dropping_stream_cancels_remote() {
+    block_on(async {
+        let (guard, dropped) = guard();
+        let stream: Stream<u32> = Box::pin(stream::unfold(guard, |guard| async move {
+            pending::<()>().await;
+            Some((0, guard))
+        }));
+        drop(round_trip::<Cbor, _>(stream).await.unwrap());
+        dropped.await;
+    });
+}
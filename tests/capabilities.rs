@@ -0,0 +1,69 @@
+use futures::executor::block_on;
+#[cfg(feature = "core")]
+use futures::future::{pending, ready};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+#[cfg(feature = "core")]
+use vessels::core::{acquire_when_available, register, CoreError};
+use vessels::{core::Core, kind::Infallible, object};
+
+#[object]
+pub trait Greeter {
+    fn greet(&self, name: String) -> Infallible<String>;
+}
+
+struct Implementor;
+
+impl Greeter for Implementor {
+    fn greet(&self, name: String) -> Infallible<String> {
+        Box::pin(async move { Ok(format!("hello, {}", name)) })
+    }
+}
+
+#[test]
+fn objects_are_described_once() {
+    block_on(async {
+        let created = Arc::new(AtomicUsize::new(0));
+        let mut core = Core::new();
+        core.register_object({
+            let created = created.clone();
+            move || {
+                created.fetch_add(1, Ordering::SeqCst);
+                Box::new(Implementor) as Box<dyn Greeter>
+            }
+        });
+        assert_eq!(created.load(Ordering::SeqCst), 1);
+        let handle = core.into_handle();
+        for _ in 0..4 {
+            let capabilities = handle.capabilities().await.unwrap();
+            assert_eq!(capabilities.len(), 1);
+            assert_eq!(capabilities[0].methods, vec!["greet".to_owned()]);
+        }
+        assert_eq!(created.load(Ordering::SeqCst), 1);
+    });
+}
+
+#[cfg(feature = "core")]
+#[test]
+fn capabilities_registered_later_are_acquired() {
+    block_on(async {
+        let greeter = acquire_when_available::<Box<dyn Greeter>>(pending());
+        register(|| Box::new(Implementor) as Box<dyn Greeter>);
+        let greeter = greeter.await.unwrap();
+        assert_eq!(
+            greeter.greet("vessel".to_owned()).await.unwrap(),
+            "hello, vessel"
+        );
+    });
+}
+
+#[cfg(feature = "core")]
+#[test]
+fn acquisition_fails_at_the_deadline() {
+    block_on(async {
+        let result = acquire_when_available::<Vec<u8>>(ready(())).await;
+        assert!(matches!(result, Err(CoreError::Timeout)));
+    });
+}
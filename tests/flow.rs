@@ -0,0 +1,78 @@
+use anyhow::{anyhow, Error};
+use futures::{
+    channel::mpsc::unbounded, executor::block_on, future::ready, sink::drain, stream, SinkExt,
+    StreamExt,
+};
+use std::{thread, time::Duration};
+use vessels::{
+    format::Cbor,
+    kind::{Sink, Stream},
+    testing::{round_trip, round_trip_with, Conditions},
+};
+
+// more items than fit in a single credit window
+const ITEMS: u32 = 100;
+
+#[test]
+fn stream_beyond_credit_window() {
+    block_on(async {
+        let stream: Stream<u32> = Box::pin(stream::iter(0..ITEMS));
+        let stream = round_trip_with::<Cbor, _>(
+            stream,
+            Conditions {
+                reorder: 8,
+                ..Conditions::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            stream.collect::<Vec<_>>().await,
+            (0..ITEMS).collect::<Vec<_>>()
+        );
+    });
+}
+
+#[test]
+fn sink_beyond_credit_window() {
+    block_on(async {
+        let (sender, receiver) = unbounded();
+        let sink: Sink<u32, Error> = Box::pin(sender.sink_map_err(Error::from));
+        let mut sink = round_trip::<Cbor, _>(sink).await.unwrap();
+        for item in 0..ITEMS {
+            sink.send(item).await.unwrap();
+        }
+        assert_eq!(
+            receiver.take(ITEMS as usize).collect::<Vec<_>>().await,
+            (0..ITEMS).collect::<Vec<_>>()
+        );
+    });
+}
+
+#[test]
+fn sink_errors_are_reported_while_credit_remains() {
+    block_on(async {
+        let (attempts, mut attempted) = unbounded();
+        let sink: Sink<u32, Error> =
+            Box::pin(drain().sink_map_err(Error::from).with(move |item: u32| {
+                attempts.unbounded_send(item).unwrap();
+                ready(Err::<u32, _>(anyhow!("rejected {}", item)))
+            }));
+        let mut sink = round_trip::<Cbor, _>(sink).await.unwrap();
+        sink.send(0).await.unwrap();
+        assert_eq!(attempted.next().await, Some(0));
+        // the failure is reported by the next poll once it arrives, long before
+        // the credit window is exhausted
+        let mut error = None;
+        for _ in 0..100 {
+            if let Err(e) = sink.flush().await {
+                error = Some(e);
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(error.unwrap().to_string().contains("rejected 0"));
+        sink.send(1).await.unwrap();
+        assert_eq!(attempted.next().await, Some(1));
+    });
+}
@@ -0,0 +1,59 @@
+use futures::{
+    executor::block_on,
+    future::{join_all, pending},
+};
+use std::sync::Arc;
+use vessels::{format::Cbor, kind::Infallible, testing::round_trip};
+
+#[test]
+fn concurrent_calls() {
+    block_on(async {
+        // calls with an odd argument never return, so later calls complete only if
+        // they are not queued behind them
+        let add: Box<dyn Fn(u32, u32) -> Infallible<u32> + Sync + Send> = Box::new(|a, b| {
+            Box::pin(async move {
+                if a % 2 == 1 {
+                    pending::<()>().await;
+                }
+                Ok(a + b)
+            })
+        });
+        let add = round_trip::<Cbor, _>(add).await.unwrap();
+        let _stalled = add(1, 0);
+        let sums = join_all((0..8).map(|a| add(a * 2, 1))).await;
+        assert_eq!(
+            sums.into_iter().map(Result::unwrap).collect::<Vec<_>>(),
+            (0..8).map(|a| a * 2 + 1).collect::<Vec<_>>()
+        );
+    });
+}
+
+#[test]
+fn concurrent_nullary_calls() {
+    block_on(async {
+        let value: Arc<Box<dyn Fn() -> Infallible<u32> + Sync + Send>> =
+            Arc::new(Box::new(|| Box::pin(async { Ok(7) })));
+        let value = round_trip::<Cbor, _>(value).await.unwrap();
+        let values = join_all((0..8).map(|_| value())).await;
+        assert!(values.into_iter().all(|value| value.unwrap() == 7));
+    });
+}
+
+#[test]
+fn mutable_and_once_calls() {
+    block_on(async {
+        let mut count = 0;
+        let counter: Box<dyn FnMut(u32) -> Infallible<u32> + Sync + Send> = Box::new(move |by| {
+            count += by;
+            let count = count;
+            Box::pin(async move { Ok(count) })
+        });
+        let mut counter = round_trip::<Cbor, _>(counter).await.unwrap();
+        assert_eq!(counter(2).await.unwrap(), 2);
+        assert_eq!(counter(3).await.unwrap(), 5);
+        let once: Box<dyn FnOnce(String) -> Infallible<String> + Sync + Send> =
+            Box::new(|name| Box::pin(async move { Ok(format!("hello, {}", name)) }));
+        let once = round_trip::<Cbor, _>(once).await.unwrap();
+        assert_eq!(once("world".to_owned()).await.unwrap(), "hello, world");
+    });
+}
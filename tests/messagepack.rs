@@ -0,0 +1,90 @@
+#![cfg(feature = "messagepack")]
+
+use futures::{executor::block_on, stream::iter, StreamExt};
+use serde::{Deserialize, Serialize};
+use vessels::{
+    format::MessagePack,
+    kind::{using, Infallible, Stream},
+    testing::{round_trip, round_trip_with, Conditions},
+    Kind,
+};
+
+#[derive(Serialize, Deserialize, Kind, Debug, PartialEq, Clone)]
+#[kind(using::Serde)]
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+#[derive(Kind, Debug, PartialEq)]
+enum Shape {
+    Empty,
+    Polygon(Vec<Point>),
+    Labelled { label: String, origin: Point },
+}
+
+#[test]
+fn derived_kinds() {
+    block_on(async {
+        let triangle = || {
+            Shape::Polygon(vec![
+                Point { x: 0, y: 0 },
+                Point { x: 1, y: 0 },
+                Point { x: 0, y: 1 },
+            ])
+        };
+        assert_eq!(
+            round_trip::<MessagePack, _>(triangle()).await.unwrap(),
+            triangle()
+        );
+        let label = || Shape::Labelled {
+            label: "origin".to_owned(),
+            origin: Point { x: 0, y: 0 },
+        };
+        assert_eq!(
+            round_trip::<MessagePack, _>(label()).await.unwrap(),
+            label()
+        );
+        assert_eq!(
+            round_trip::<MessagePack, _>(Shape::Empty).await.unwrap(),
+            Shape::Empty
+        );
+    });
+}
+
+#[test]
+fn reordered_forks() {
+    block_on(async {
+        let nested: Vec<Vec<String>> = (0..16)
+            .map(|n| (0..n).map(|m| m.to_string()).collect())
+            .collect();
+        assert_eq!(
+            round_trip_with::<MessagePack, _>(
+                nested.clone(),
+                Conditions {
+                    reorder: 8,
+                    ..Conditions::default()
+                }
+            )
+            .await
+            .unwrap(),
+            nested
+        );
+    });
+}
+
+#[test]
+fn functions_and_streams() {
+    block_on(async {
+        let double: Box<dyn Fn(u32) -> Infallible<u32> + Sync + Send> =
+            Box::new(|n| Box::pin(async move { Ok(n * 2) }));
+        let double = round_trip::<MessagePack, _>(double).await.unwrap();
+        assert_eq!(double(21).await.unwrap(), 42);
+        let stream: Stream<u32> = Box::pin(iter(0..64));
+        let stream = round_trip::<MessagePack, _>(stream).await.unwrap();
+        assert_eq!(
+            stream.collect::<Vec<_>>().await,
+            (0..64).collect::<Vec<_>>()
+        );
+    });
+}
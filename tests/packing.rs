@@ -0,0 +1,53 @@
+use futures::executor::block_on;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use vessels::{
+    format::{Cbor, Json},
+    testing::{round_trip, round_trip_with, Conditions},
+    Kind,
+};
+
+#[test]
+fn primitives_are_packable() {
+    assert!(u32::packing().is_some());
+    assert!(String::packing().is_some());
+    assert!(Vec::<u32>::packing().is_none());
+}
+
+#[test]
+fn packed_collections() {
+    block_on(async {
+        let vec: Vec<u32> = (0..64).collect();
+        assert_eq!(round_trip::<Cbor, _>(vec.clone()).await.unwrap(), vec);
+        let set: HashSet<String> = vec!["a".to_owned(), "b".to_owned()].into_iter().collect();
+        assert_eq!(round_trip::<Json, _>(set.clone()).await.unwrap(), set);
+        let mut map = HashMap::new();
+        map.insert("one".to_owned(), 1u64);
+        map.insert("two".to_owned(), 2u64);
+        assert_eq!(round_trip::<Cbor, _>(map.clone()).await.unwrap(), map);
+        let array = [1u8, 2, 3, 4];
+        assert_eq!(round_trip::<Cbor, _>(array).await.unwrap(), array);
+    });
+}
+
+#[test]
+fn forked_collections() {
+    block_on(async {
+        let nested: Vec<Vec<u32>> = vec![vec![1, 2], vec![], vec![3]];
+        assert_eq!(
+            round_trip_with::<Cbor, _>(
+                nested.clone(),
+                Conditions {
+                    reorder: 4,
+                    ..Conditions::default()
+                }
+            )
+            .await
+            .unwrap(),
+            nested
+        );
+        let mut map = BTreeMap::new();
+        map.insert(1u32, vec!["x".to_owned()]);
+        map.insert(2u32, vec![]);
+        assert_eq!(round_trip::<Cbor, _>(map.clone()).await.unwrap(), map);
+    });
+}
@@ -0,0 +1,55 @@
+use futures::executor::block_on;
+use vessels::{
+    format::Cbor,
+    kind::Infallible,
+    object,
+    reflect::{self, Cast, Erased, Trait},
+    testing::round_trip,
+};
+
+#[object]
+pub trait Greeter {
+    fn greet(&self, name: String) -> Infallible<String>;
+}
+
+struct Implementor;
+
+impl Greeter for Implementor {
+    fn greet(&self, name: String) -> Infallible<String> {
+        Box::pin(async move { Ok(format!("hello, {}", name)) })
+    }
+}
+
+fn erased() -> Box<dyn Erased> {
+    Trait::<dyn Greeter>::erase(Box::new(Implementor) as Box<dyn Greeter>)
+}
+
+#[test]
+fn call_remote_erased() {
+    reflect::register::<dyn Greeter>();
+    block_on(async {
+        let remote = round_trip::<Cbor, Box<dyn Erased>>(erased()).await.unwrap();
+        let index = remote.by_name("greet").unwrap();
+        assert!(remote.types(index).is_ok());
+        let output = remote
+            .call(index, vec![Box::new("world".to_owned())])
+            .unwrap()
+            .downcast::<Infallible<String>>()
+            .unwrap();
+        assert_eq!(output.await.unwrap(), "hello, world");
+    });
+}
+
+#[test]
+fn downcast_remote_erased() {
+    reflect::register::<dyn Greeter>();
+    block_on(async {
+        let remote = round_trip::<Cbor, Box<dyn Erased>>(erased()).await.unwrap();
+        assert_eq!(remote.name_of(0).unwrap(), "greet");
+        let greeter: Box<dyn Greeter> = remote.downcast().unwrap();
+        assert_eq!(
+            greeter.greet("again".to_owned()).await.unwrap(),
+            "hello, again"
+        );
+    });
+}
@@ -0,0 +1,84 @@
+use futures::executor::block_on;
+use vessels::{
+    core::{Core, CoreError, Terms},
+    kind::Fallible,
+};
+
+type Call = Box<dyn Fn(u32) -> Fallible<u32, CoreError> + Sync + Send>;
+
+fn call() -> Call {
+    Box::new(|n| Box::pin(async move { Ok(n + 1) }))
+}
+
+fn revoked<T>(result: Result<T, CoreError>) -> bool {
+    match result {
+        Err(CoreError::Revoked) => true,
+        _ => false,
+    }
+}
+
+#[test]
+fn calls_fail_as_revoked() {
+    block_on(async {
+        let mut core = Core::new();
+        let revoker = core.register_revocable(call, Terms::default());
+        let handle = core.into_handle();
+        let call = handle.acquire::<Call>().await.unwrap();
+        assert_eq!(call(1).await.unwrap(), 2);
+        revoker.revoke();
+        assert!(revoker.is_revoked());
+        assert!(revoked(call(1).await));
+        assert!(revoked(handle.acquire::<Call>().await));
+    });
+}
+
+#[test]
+fn exhausted_frame_budget_revokes() {
+    const BUDGET: u64 = 16;
+    block_on(async {
+        let mut core = Core::new();
+        let revoker = core.register_revocable(
+            call,
+            Terms {
+                frame_budget: Some(BUDGET),
+                ..Terms::default()
+            },
+        );
+        let handle = core.into_handle();
+        let call = handle.acquire::<Call>().await.unwrap();
+        // every call sends at least one frame, so one of these must exceed the
+        // budget
+        let mut results = vec![];
+        for n in 0..=BUDGET as u32 {
+            results.push(call(n).await);
+        }
+        assert!(results[0].is_ok());
+        assert!(revoked(results.pop().unwrap()));
+        assert!(revoker.is_revoked());
+    });
+}
+
+#[cfg(feature = "core")]
+#[test]
+fn expired_calls_fail_as_revoked() {
+    use std::time::Duration;
+    use vessels::core::hal::time::Clock;
+
+    const TTL: Duration = Duration::from_millis(50);
+    block_on(async {
+        let mut core = Core::new();
+        let revoker = core.register_revocable(
+            call,
+            Terms {
+                ttl: Some(TTL),
+                ..Terms::default()
+            },
+        );
+        let handle = core.into_handle();
+        let call = handle.acquire::<Call>().await.unwrap();
+        assert_eq!(call(1).await.unwrap(), 2);
+        <dyn Clock>::new().unwrap().sleep(TTL * 4).await.unwrap();
+        assert!(revoker.is_revoked());
+        assert!(revoked(call(1).await));
+    });
+}
@@ -0,0 +1,37 @@
+use futures::executor::block_on;
+use vessels::{
+    channel::{
+        id_channel::tap::{replay, Direction, Recorder, Tap},
+        IdChannel,
+    },
+    format::Json,
+    kind::Infallible,
+    OnTo,
+};
+
+type Call = Box<dyn Fn(u32) -> Infallible<u32> + Send + Sync>;
+
+#[test]
+fn taps_resolve_fork_types() {
+    block_on(async {
+        let call: Call = Box::new(|n| Box::pin(async move { Ok(n * 2) }));
+        let local = Recorder::new();
+        let remote = Recorder::new();
+        let encoded =
+            Tap::<_, Json>::from_channel(call.on_to::<IdChannel>().await, local.observer());
+        let call: Call = Tap::<_, Json>::new(encoded, remote.observer())
+            .construct()
+            .await
+            .unwrap();
+        assert_eq!(call(21).await.unwrap(), 42);
+        for recorder in &[&local, &remote] {
+            let frames = recorder.frames();
+            assert!(frames
+                .iter()
+                .any(|frame| frame.direction == Direction::Inbound));
+            assert!(frames.iter().any(|frame| frame.ty.is_some()));
+        }
+        let replayed: Call = replay::<_, Json>(remote.inbound()).await.unwrap();
+        drop(replayed);
+    });
+}